@@ -1,23 +1,75 @@
 use s3::{creds::Credentials, region::Region, Bucket};
-use std::env;
+use std::{env, path::PathBuf, time::Duration};
 
 pub fn get_s3_bucket() -> Bucket {
-    let creds = Credentials::new(Some("minioadmin"), Some("minioadmin"), None, None, None).unwrap();
+    let access_key = env::var("IVE_S3_ACCESS_KEY").unwrap_or("minioadmin".to_owned());
+    let secret_key = env::var("IVE_S3_SECRET_KEY").unwrap_or("minioadmin".to_owned());
+    let creds = Credentials::new(Some(&access_key), Some(&secret_key), None, None, None).unwrap();
 
     let bucket = Bucket::new(
-        "ive",
+        &env::var("IVE_S3_BUCKET").unwrap_or("ive".to_owned()),
         Region::Custom {
-            region: "my-store".to_owned(),
+            region: env::var("IVE_S3_REGION").unwrap_or("my-store".to_owned()),
             endpoint: env::var("IVE_S3_URL").expect("Expected an s3 url in the environment"),
         },
         creds,
     )
-    .unwrap()
-    .with_path_style();
+    .unwrap();
 
-    bucket
+    // Minio needs path style requests, other providers may not support them
+    match env::var("IVE_S3_PATH_STYLE").as_deref() {
+        Ok("false") | Ok("0") => bucket,
+        _ => bucket.with_path_style(),
+    }
 }
 
 pub fn get_redis_client() -> redis::Client {
     redis::Client::open(env::var("IVE_REDIS_URL").expect("Expected a redis url in the environment")).unwrap()
-}
\ No newline at end of file
+}
+
+pub fn get_allowed_input_paths() -> Vec<PathBuf> {
+    match env::var_os("IVE_ALLOWED_INPUT_PATHS") {
+        Some(paths) => env::split_paths(&paths).collect(),
+        None => vec![PathBuf::from("tmpfs")],
+    }
+}
+
+pub fn get_input_max_size() -> u64 {
+    env::var("IVE_INPUT_MAX_SIZE").ok().and_then(|s| s.parse().ok()).unwrap_or(512 * 2_u64.pow(20))
+}
+
+pub fn get_input_cache_budget() -> u64 {
+    env::var("IVE_INPUT_CACHE_BUDGET").ok().and_then(|s| s.parse().ok()).unwrap_or(4 * 2_u64.pow(30))
+}
+
+pub fn get_storage_kind() -> String {
+    env::var("IVE_STORAGE").unwrap_or("s3".to_owned())
+}
+
+pub fn get_storage_path() -> PathBuf {
+    PathBuf::from(env::var("IVE_STORAGE_PATH").unwrap_or("storage".to_owned()))
+}
+
+pub fn get_storage_public_url() -> Option<String> {
+    env::var("IVE_STORAGE_PUBLIC_URL").ok()
+}
+
+/// How long a result is reused for identical jobs
+pub fn get_result_cache_ttl() -> Duration {
+    let secs = env::var("IVE_RESULT_CACHE_TTL").ok().and_then(|s| s.parse().ok()).unwrap_or(7 * 24 * 60 * 60);
+    Duration::from_secs(secs)
+}
+
+/// How long presigned download links stay valid, s3 caps this to 7 days
+pub fn get_presign_expiry() -> Duration {
+    let secs = env::var("IVE_PRESIGN_EXPIRY").ok().and_then(|s| s.parse().ok()).unwrap_or(24 * 60 * 60);
+    Duration::from_secs(secs)
+}
+
+/// Directories searched for the fonts used by text overlays
+pub fn get_font_paths() -> Vec<PathBuf> {
+    match env::var_os("IVE_FONT_PATHS") {
+        Some(paths) => env::split_paths(&paths).collect(),
+        None => vec![PathBuf::from("fonts"), PathBuf::from("/usr/share/fonts/truetype/dejavu")],
+    }
+}
@@ -0,0 +1,90 @@
+use std::{path::{Path, PathBuf}, time::Duration};
+
+use models::{error, VideoURI};
+use snafu::ResultExt;
+use tokio::io::AsyncReadExt;
+
+/// Presigned urls handed to ffmpeg only need to live as long as the job
+const PRESIGN_EXPIRY: Duration = Duration::from_secs(60 * 60);
+/// Bytes read from a local input to recognize a playlist
+const PLAYLIST_PROBE_SIZE: usize = 4096;
+
+/// A validated input location that can be handed to ffmpeg or ffprobe
+#[derive(Debug, Clone)]
+pub enum Input {
+    /// Local file inside one of the allowed directories or the local storage
+    Path(PathBuf),
+    /// Remote http(s) url, including presigned s3 urls
+    Url(String),
+}
+
+impl Input {
+    pub async fn resolve(uri: &VideoURI) -> Result<Input, error::Worker> {
+        match uri {
+            VideoURI::Path(p) => resolve_path(Path::new(p), &config::get_allowed_input_paths()).await,
+            VideoURI::Url(u) => {
+                if u.starts_with("https://") || u.starts_with("http://") {
+                    Ok(Input::Url(u.to_owned()))
+                } else {
+                    Err(error::Input::UnsupportedURI).context(error::InputSnafu)
+                }
+            }
+            VideoURI::S3(key) => {
+                let storage = storage::get_storage();
+                if let Some(path) = storage.local_path(key) {
+                    check_not_playlist(&path).await?;
+                    return Ok(Input::Path(path));
+                }
+                let url = storage.presign(key, PRESIGN_EXPIRY).await.context(error::StorageSnafu)?;
+                Ok(Input::Url(url))
+            }
+        }
+    }
+
+    pub fn location(&self) -> Result<&str, error::Worker> {
+        match self {
+            Input::Path(p) => p.to_str().ok_or(error::Worker::Message { msg: "input path str conversion error".to_owned() }),
+            Input::Url(u) => Ok(u),
+        }
+    }
+}
+
+pub(crate) async fn resolve_path(path: &Path, allowed_paths: &[PathBuf]) -> Result<Input, error::Worker> {
+    let display = path.display().to_string();
+    let path = match tokio::fs::canonicalize(path).await {
+        Ok(p) => p,
+        Err(_) => return Err(error::Input::NotFound(display)).context(error::InputSnafu),
+    };
+
+    // Only files below an allowed directory can be read, anything else would let
+    // a job read arbitrary files from the worker
+    for allowed in allowed_paths {
+        let Ok(allowed) = tokio::fs::canonicalize(allowed).await else { continue };
+        if path.starts_with(&allowed) && path.is_file() {
+            check_not_playlist(&path).await?;
+            return Ok(Input::Path(path));
+        }
+    }
+    Err(error::Input::PathNotAllowed(display)).context(error::InputSnafu)
+}
+
+/// Local inputs are read by ffmpeg without any protocol restriction, a hls, dash or
+/// concat playlist would let it read the other files under tmpfs into the output
+async fn check_not_playlist(path: &Path) -> Result<(), error::Worker> {
+    let mut file = tokio::fs::File::open(path).await.context(error::IoSnafu)?;
+    let mut head = Vec::with_capacity(PLAYLIST_PROBE_SIZE);
+    (&mut file).take(PLAYLIST_PROBE_SIZE as u64).read_to_end(&mut head).await.context(error::IoSnafu)?;
+    if is_playlist(&head) {
+        return Err(error::Input::Playlist(path.display().to_string())).context(error::InputSnafu);
+    }
+    Ok(())
+}
+
+/// Whether the start of a file is a playlist ffmpeg would follow
+pub(crate) fn is_playlist(head: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(head);
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    let start = text.get(..16).unwrap_or(text).to_ascii_lowercase();
+    start.starts_with("#extm3u") || start.starts_with("ffconcat") || text.contains("<MPD")
+}
+
@@ -1,12 +1,14 @@
-use snafu::ResultExt;
+use snafu::{Location, ResultExt};
 use std::{process::Stdio, path::{PathBuf, Path}};
 use models::*;
 
 extern crate ffmpeg_next as ffmpeg;
 
 pub mod utils;
+pub mod input;
 
 use ffmpeg_cli::{FfmpegBuilder, File, Parameter};
+use input::Input;
 
 use async_trait::async_trait;
 
@@ -20,15 +22,92 @@ impl Run for FfmpegBuilder<'_> {
     async fn run_and_upload(self, id: &str) -> Result<(), error::Worker> {
         let ffmpeg = self.run().await.context(error::FfmpegSnafu)?;
         let mut child = ffmpeg.process;
-        let mut stdout =  child.stdout.take().ok_or(error::Worker::Message { msg: "no child stdout".to_owned()})?;
+        let stdout =  child.stdout.take().ok_or(error::Worker::Message { msg: "no child stdout".to_owned()})?;
+        let mut reader = utils::HashingReader::new(stdout);
 
-        let bucket = config::get_s3_bucket();
-        let _res = bucket
-            .put_object_stream(&mut stdout, &id)
-            .await.context(error::S3Snafu)?;
-        Ok(())
+        let storage = storage::get_storage();
+        let key = storage::output_key(id);
+        let upload = storage.put_stream(&mut reader, &key).await;
+
+        // A crashed ffmpeg closes its stdout early, the upload alone can't tell
+        let status = if upload.is_ok() {
+            child.wait().await.context(error::IoSnafu)
+        } else {
+            let _ = child.kill().await;
+            child.wait().await.context(error::IoSnafu)
+        };
+        let res = match (upload, status) {
+            (Err(e), _) => Err(e).context(error::StorageSnafu),
+            (_, Err(e)) => Err(e),
+            (_, Ok(status)) if !status.success() => Err(error::Ffmpeg::Exit { status, location: snafu::location!() }).context(error::FfmpegSnafu),
+            (Ok(_), Ok(_)) => check_upload(storage.as_ref(), &key, reader, true).await,
+        };
+
+        if res.is_err() {
+            let _ = storage.delete(&key).await;
+        }
+        res
+    }
+}
+
+/// Records the metadata of a finished upload and makes sure it can be decoded
+async fn check_upload<R: tokio::io::AsyncRead + Unpin>(storage: &dyn storage::Storage, key: &str, reader: utils::HashingReader<R>, probe: bool) -> Result<(), error::Worker> {
+    let (size, sha256) = reader.finish();
+    storage.set_metadata(key, &storage::ObjectMetadata { size, sha256 }).await.context(error::StorageSnafu)?;
+    if probe {
+        probe_output(key).await?;
+    }
+    Ok(())
+}
+
+/// Uploads a file written by ffmpeg under `key`
+async fn upload_file(path: &Path, key: &str, probe: bool) -> Result<(), error::Worker> {
+    let file = tokio::fs::File::open(path).await.context(error::IoSnafu)?;
+    let mut reader = utils::HashingReader::new(file);
+
+    let storage = storage::get_storage();
+    let res = match storage.put_stream(&mut reader, key).await.context(error::StorageSnafu) {
+        Ok(_) => check_upload(storage.as_ref(), key, reader, probe).await,
+        Err(e) => Err(e),
+    };
+
+    if res.is_err() {
+        let _ = storage.delete(key).await;
+    }
+    res
+}
+
+/// Runs ffmpeg until it exits, failing if it didn't succeed
+async fn run_to_completion(builder: FfmpegBuilder<'_>) -> Result<(), error::Worker> {
+    let status = builder.run().await.context(error::FfmpegSnafu)?.process.wait().await.context(error::IoSnafu)?;
+    if !status.success() {
+        return Err(error::Ffmpeg::Exit { status, location: snafu::location!() }).context(error::FfmpegSnafu);
+    }
+    Ok(())
+}
+
+/// Checks the start of an uploaded output can be decoded
+async fn probe_output(key: &str) -> Result<(), error::Worker> {
+    let source = Input::resolve(&VideoURI::S3(key.to_owned())).await?;
+    let builder = FfmpegBuilder::new()
+        .option(Parameter::single("nostdin"))
+        .option(Parameter::key_value("v", "error"))
+        .input(File::new(source.location()?))
+        .output(File::new("-").option(Parameter::key_value("t", "1")).option(Parameter::key_value("f", "null")));
+
+    run_to_completion(builder).await
+}
+
+/// Runs an analysis pass that outputs nothing, returning what ffmpeg logged
+async fn run_analysis(builder: FfmpegBuilder<'_>) -> Result<String, error::Worker> {
+    let ffmpeg = builder.stderr(Stdio::piped()).run().await.context(error::FfmpegSnafu)?;
+    let output = ffmpeg.process.wait_with_output().await.context(error::IoSnafu)?;
+    if !output.status.success() {
+        return Err(error::Ffmpeg::Exit { status: output.status, location: snafu::location!() }).context(error::FfmpegSnafu);
     }
+    Ok(String::from_utf8_lossy(&output.stderr).into_owned())
 }
+
 pub trait FfmpegBuilderDefault<'a> {
     fn default(url: &str) -> FfmpegBuilder;
     fn default_audio(url: &str) -> FfmpegBuilder;
@@ -58,26 +137,73 @@ impl<'a> FfmpegBuilderDefault<'a> for FfmpegBuilder<'a> {
 }
 
 pub async fn get_streams(video: &Video) -> Result<Vec::<MediaStream>, error::Worker> {
-    let url = match &video.url {
-        VideoURI::Url(p) => p,
-        _ => return Err(error::Encode::EncodeToSize(error::EncodeToSize::UnsupportedURI)).context(error::EncodeSnafu)?,
-    };
-    ffmpeg::init().unwrap();
-    let input = ffmpeg::format::input(url).unwrap();
+    let source = Input::resolve(&video.url).await?;
+    let url = source.location()?;
+    let input = probe(url)?;
 
-    Ok(input.streams().into_iter().map(|stream| {
-        let codec = ffmpeg::codec::context::Context::from_parameters(stream.parameters()).unwrap();
+    input.streams().into_iter().map(|stream| {
+        let codec = ffmpeg::codec::context::Context::from_parameters(stream.parameters())
+            .map_err(|e| error::Input::Probe(e.to_string()))
+            .context(error::InputSnafu)?;
         let duration = input.duration();
-        MediaStream {
+        let codec_name = codec.id().name().to_owned();
+        let kind = match codec.medium() {
+            ffmpeg::media::Type::Audio => StreamKind::Audio,
+            ffmpeg::media::Type::Video => StreamKind::Video,
+            ffmpeg::media::Type::Subtitle => StreamKind::Subtitle,
+            ffmpeg::media::Type::Data => StreamKind::Data,
+            ffmpeg::media::Type::Attachment => StreamKind::Attachment,
+            _ => StreamKind::Unknown,
+        };
+        let mut media_stream = MediaStream {
             id: stream.index(),
-            kind: match codec.medium() {
-                ffmpeg::media::Type::Audio => StreamKind::Audio,
-                ffmpeg::media::Type::Video => StreamKind::Video,
-                _ => StreamKind::Unknown,
-            },
+            kind,
             duration,
+            codec: Some(codec_name),
+            width: None,
+            height: None,
+            frame_rate: None,
+            channels: None,
+            sample_rate: None,
+            bit_rate: None,
+            language: stream.metadata().get("language").map(|l| l.to_owned()),
+            rotation: None,
+        };
+        match media_stream.kind {
+            StreamKind::Video => {
+                let frame_rate = stream.avg_frame_rate();
+                if frame_rate.denominator() != 0 {
+                    media_stream.frame_rate = Some(f64::from(frame_rate));
+                }
+                media_stream.rotation = stream
+                    .side_data()
+                    .find(|d| d.kind() == ffmpeg::packet::side_data::Type::DisplayMatrix)
+                    .and_then(|d| utils::get_display_rotation(d.data()));
+                if let Ok(video) = codec.decoder().video() {
+                    media_stream.width = Some(video.width());
+                    media_stream.height = Some(video.height());
+                    media_stream.bit_rate = Some(video.bit_rate() as u64).filter(|b| *b != 0);
+                }
+            }
+            StreamKind::Audio => {
+                if let Ok(audio) = codec.decoder().audio() {
+                    media_stream.channels = Some(audio.channels());
+                    media_stream.sample_rate = Some(audio.rate()).filter(|r| *r != 0);
+                    media_stream.bit_rate = Some(audio.bit_rate() as u64).filter(|b| *b != 0);
+                }
+            }
+            _ => {}
         }
-    }).collect())
+        Ok(media_stream)
+    }).collect()
+}
+
+/// Opens an input with libavformat, a file it can't read is an input error rather than a crash
+fn probe(url: &str) -> Result<ffmpeg::format::context::Input, error::Worker> {
+    ffmpeg::init()
+        .and_then(|_| ffmpeg::format::input(&url))
+        .map_err(|e| error::Input::Probe(e.to_string()))
+        .context(error::InputSnafu)
 }
 
 pub fn get_working_dir(id: &String) -> Result<PathBuf, std::io::Error> {
@@ -87,14 +213,10 @@ pub fn get_working_dir(id: &String) -> Result<PathBuf, std::io::Error> {
 }
 
 pub async fn encode_to_size(video: &Video, params: &EncodeToSizeParameters) -> Result<(), error::Worker> {
-    let url = match &video.url {
-        VideoURI::Url(p) => p,
-        _ => return Err(error::Encode::EncodeToSize(error::EncodeToSize::UnsupportedURI)).context(error::EncodeSnafu)?,
-    };
+    let source = Input::resolve(&video.url).await?;
+    let url = source.location()?;
 
-    ffmpeg::init().unwrap();
-
-    let input = ffmpeg::format::input(url).unwrap();
+    let input = probe(url)?;
     let duration = utils::get_duration(&input);
     let audio_rate = utils::get_audio_bitrate(&input);
 
@@ -128,8 +250,9 @@ pub async fn encode_to_size(video: &Video, params: &EncodeToSizeParameters) -> R
     .option(Parameter::key_value("passlogfile", passfile_prefix));
     builder.outputs = vec![file];
 
-    builder.run().await.context(error::FfmpegSnafu)?.process.wait().await.context(error::IoSnafu)?;
- 
+    // The second pass can't run without the log of a complete first pass
+    run_to_completion(builder).await?;
+
     let mut builder = FfmpegBuilder::default(url);
 
     let file = File::new("pipe:1").option(Parameter::key_value("f", "mp4"))
@@ -147,20 +270,22 @@ pub async fn encode_to_size(video: &Video, params: &EncodeToSizeParameters) -> R
 }
 
 pub async fn combine(video: &Video, params: &CombineParameters) -> Result<(), error::Worker> {
-    dbg!(&params.output_kind);
-    let url = match &video.url {
-        VideoURI::Path(p) => p,
-        VideoURI::Url(u) => u,
-    };
+    let source = Input::resolve(&video.url).await?;
+    let url = source.location()?;
     let mut builder = match &params.output_kind {
         StreamKind::Video => FfmpegBuilder::default(url),
         StreamKind::Audio => FfmpegBuilder::default_audio(url),
-        StreamKind::Unknown => todo!(),
+        _ => return Err(error::Encode::Combine(error::Combine::UnsupportedOutputKind)).context(error::EncodeSnafu)?,
     };
     builder.inputs.clear();
 
-    for (i, v) in params.videos.iter().enumerate() {
-        builder = builder.input(File::new(&v.url));
+    let mut sources = Vec::with_capacity(params.videos.len());
+    for v in params.videos.iter() {
+        sources.push(Input::resolve(&v.url).await?);
+    }
+
+    for (i, (v, source)) in params.videos.iter().zip(sources.iter()).enumerate() {
+        builder = builder.input(File::new(source.location()?));
         for s in v.selected_streams.iter() {
             builder.outputs.first_mut().ok_or(error::Worker::Message { msg: "outputs vec empty".to_owned()})?.options.push(Parameter::key_value("map", format!("{i}:{s}")));
         }
@@ -169,87 +294,1795 @@ pub async fn combine(video: &Video, params: &CombineParameters) -> Result<(), er
     Ok(())
 }
 
+const BITMAP_SUBTITLE_CODECS: [&str; 3] = ["dvd_subtitle", "hdmv_pgs_subtitle", "dvb_subtitle"];
+
 pub async fn remux(video: &Video, params: &RemuxParameters) -> Result<(), error::Worker> {
-    let url = match &video.url {
-        VideoURI::Path(p) => p,
-        VideoURI::Url(u) => u,
+    let source = Input::resolve(&video.url).await?;
+    let url = source.location()?;
+
+    let container = params.container;
+    let streams = get_streams(video).await?;
+
+    let mut file = File::new("pipe:1").option(Parameter::key_value("f", container.get_format()));
+    if container.is_mp4_family() {
+        file = file.option(Parameter::key_value("movflags", "frag_keyframe+empty_moov"));
+    }
+
+    // Copy what the container accepts, transcode the rest when it can hold that kind of stream
+    let mut output_index = 0;
+    for stream in streams.iter() {
+        if container.is_single_stream() && output_index == 1 {
+            break;
+        }
+        let Some(codec) = &stream.codec else { continue };
+        let encoder = if container.accepts_codec(&stream.kind, codec) {
+            "copy"
+        } else if BITMAP_SUBTITLE_CODECS.contains(&codec.as_str()) {
+            // Image based subtitles can't be converted to text ones
+            continue;
+        } else {
+            match container.get_default_encoder(&stream.kind) {
+                Some(e) => e,
+                None => continue,
+            }
+        };
+        file = file
+            .option(Parameter::key_value("map", format!("0:{}", stream.id)))
+            .option(Parameter::key_value(format!("c:{output_index}"), encoder));
+        output_index += 1;
+    }
+    if output_index == 0 {
+        return Err(error::Encode::Remux(error::Remux::NoCompatibleStream)).context(error::EncodeSnafu)?;
+    }
+
+    let mut builder = FfmpegBuilder::default(url);
+    builder.outputs = vec![file];
+
+    builder.run_and_upload(&video.id).await?;
+    Ok(())
+}
+
+/// Keeps the seeks of a smart cut away from the exact timestamp of a keyframe, they are rounded to microseconds
+const CUT_KEYFRAME_MARGIN: f64 = 0.0005;
+
+/// Timestamps of the keyframes of a video stream, in seconds
+async fn get_keyframes(url: &str, stream: usize) -> Result<Vec<f64>, error::Worker> {
+    let builder = FfmpegBuilder::new()
+        .option(Parameter::single("nostdin"))
+        .option(Parameter::single("hide_banner"))
+        .input(File::new(url).option(Parameter::key_value("skip_frame", "nokey")))
+        .output(File::new("-")
+            .option(Parameter::key_value("map", format!("0:{stream}")))
+            .option(Parameter::key_value("filter:v", "settb=AVTB,showinfo"))
+            .option(Parameter::key_value("f", "null")));
+
+    let stderr = run_analysis(builder).await?;
+    Ok(utils::parse_keyframes(&stderr))
+}
+
+/// Ranges a keyframe cut actually keeps, their starts move back to the previous keyframe
+pub async fn get_cut_bounds(video: &Video, params: &CutParameters) -> Result<Vec<CutSegment>, error::Worker> {
+    let source = Input::resolve(&video.url).await?;
+    let url = source.location()?;
+    let streams = get_streams(video).await?;
+    let duration = container_duration(&streams);
+
+    let ranges = params.get_kept_ranges(duration);
+    let ranges = match first_stream(&streams, &StreamKind::Video) {
+        Some(s) => utils::snap_to_keyframes(&ranges, &get_keyframes(url, s.id).await?),
+        None => ranges,
     };
+    Ok(ranges
+        .into_iter()
+        .map(|(start, end)| CutSegment {
+            start: Some(std::time::Duration::from_secs_f64(start)),
+            end: Some(std::time::Duration::from_secs_f64(end)),
+        })
+        .collect())
+}
+
+pub async fn cut(video: &Video, params: &CutParameters) -> Result<(), error::Worker> {
+    let source = Input::resolve(&video.url).await?;
+    let url = source.location()?;
+
+    let streams = get_streams(video).await?;
+    let duration = container_duration(&streams);
+    let ranges = params.get_kept_ranges(duration);
+    if ranges.is_empty() {
+        return Err(error::Encode::Cut(error::Cut::Empty)).context(error::EncodeSnafu)?;
+    }
+
+    let container = params.get_container(video.get_container());
+    let mut file = File::new("pipe:1").option(Parameter::key_value("f", container.get_format()));
+    if container.is_mp4_family() {
+        file = file.option(Parameter::key_value("movflags", "frag_keyframe+empty_moov"));
+    }
+
+    // Streams the container can't hold are left out
+    let video_stream = first_stream(&streams, &StreamKind::Video).filter(|s| match params.mode {
+        CutMode::Keyframe => matches!(&s.codec, Some(c) if container.accepts_codec(&StreamKind::Video, c)),
+        CutMode::Smart | CutMode::Reencode => container.get_default_encoder(&StreamKind::Video).is_some(),
+    });
+    let audio_stream = first_stream(&streams, &StreamKind::Audio).filter(|s| match params.mode {
+        CutMode::Keyframe => matches!(&s.codec, Some(c) if container.accepts_codec(&StreamKind::Audio, c)),
+        CutMode::Smart | CutMode::Reencode => container.get_default_encoder(&StreamKind::Audio).is_some(),
+    });
+    if video_stream.is_none() && audio_stream.is_none() {
+        return Err(error::Encode::Cut(error::Cut::NoStream)).context(error::EncodeSnafu)?;
+    }
+
+    let dir = get_working_dir(&video.id).context(error::IoSnafu)?;
+    let list = job_file(&dir, "cut.txt")?;
+
+    // Only h264 is re-encoded with matching parameters, other codecs are re-encoded entirely
+    let smart = video_stream.filter(|s| {
+        params.mode == CutMode::Smart
+            && s.codec.as_deref() == Some("h264")
+            && container.get_default_encoder(&StreamKind::Video) == Some("libx264")
+            && container.accepts_codec(&StreamKind::Audio, "aac")
+    });
+
+    let mut builder = FfmpegBuilder::default(url);
+    let file = if params.mode == CutMode::Keyframe || smart.is_some() {
+        let mut entries = String::new();
+        if let Some(stream) = smart {
+            // The parts of every range are encoded to MPEG-TS, which carries the parameters
+            // of each encoder in band so that they can be joined without re-encoding
+            let keyframes = get_keyframes(url, stream.id).await?;
+            let parts = ranges.iter().flat_map(|(start, end)| utils::smart_cut_parts(*start, *end, &keyframes));
+            for (i, (start, end, copy)) in parts.enumerate() {
+                let path = job_file(&dir, &format!("part{i}.ts"))?;
+                let start = if copy { start + CUT_KEYFRAME_MARGIN } else { start };
+                let length = end - CUT_KEYFRAME_MARGIN - start;
+
+                let mut part = File::new(&path)
+                    .option(Parameter::key_value("t", length.to_string()))
+                    .option(Parameter::key_value("map", format!("0:{}", stream.id)))
+                    .option(Parameter::key_value("c:v", if copy { "copy" } else { "libx264" }));
+                if !copy {
+                    part = part.option(Parameter::key_value("pix_fmt", "yuv420p"));
+                }
+                if let Some(s) = audio_stream {
+                    part = part
+                        .option(Parameter::key_value("map", format!("0:{}", s.id)))
+                        .option(Parameter::key_value("c:a", "aac"))
+                        .option(Parameter::key_value("b:a", "192k"))
+                        .option(Parameter::key_value("ar", "48000"))
+                        .option(Parameter::key_value("ac", "2"));
+                }
+                let builder = FfmpegBuilder::new()
+                    .option(Parameter::single("nostdin"))
+                    .option(Parameter::single("y"))
+                    .input(File::new(url).option(Parameter::key_value("ss", start.to_string())))
+                    .output(part.option(Parameter::key_value("f", "mpegts")));
+                run_to_completion(builder).await?;
+
+                entries += &concat_list_entry(&path);
+            }
+        } else {
+            // The concat demuxer starts each range at the keyframe before its inpoint
+            for (start, end) in ranges.iter() {
+                entries += &concat_list_entry(url);
+                if *start > 0.0 {
+                    entries += &format!("inpoint {start}\n");
+                }
+                if *end < duration {
+                    entries += &format!("outpoint {end}\n");
+                }
+            }
+        }
+        tokio::fs::write(&list, entries).await.context(error::IoSnafu)?;
+
+        builder.inputs = vec![File::new(&list)
+            .option(Parameter::key_value("f", "concat"))
+            .option(Parameter::key_value("safe", "0"))
+            .option(Parameter::key_value("protocol_whitelist", "file,http,https,tcp,tls"))];
+        let mut file = file.option(Parameter::key_value("c", "copy"));
+        if smart.is_some() {
+            file = file.option(Parameter::key_value("map", "0:v:0")).option(Parameter::key_value("map", "0:a:0?"));
+        } else {
+            for s in video_stream.iter().chain(audio_stream.iter()) {
+                file = file.option(Parameter::key_value("map", format!("0:{}", s.id)));
+            }
+        }
+        file
+    } else {
+        // Every range is trimmed from the decoded streams and the pieces are joined
+        let mut graph = String::new();
+        let mut segments = String::new();
+        for (i, (start, end)) in ranges.iter().enumerate() {
+            if let Some(s) = video_stream {
+                graph += &format!("[0:{}]trim=start={start}:end={end},setpts=PTS-STARTPTS[v{i}];", s.id);
+                segments += &format!("[v{i}]");
+            }
+            if let Some(s) = audio_stream {
+                graph += &format!("[0:{}]atrim=start={start}:end={end},asetpts=PTS-STARTPTS[a{i}];", s.id);
+                segments += &format!("[a{i}]");
+            }
+        }
+        graph += &format!("{segments}concat=n={}:v={}:a={}", ranges.len(), video_stream.is_some() as u8, audio_stream.is_some() as u8);
+
+        let mut file = file;
+        if let (Some(_), Some(encoder)) = (video_stream, container.get_default_encoder(&StreamKind::Video)) {
+            graph += "[v]";
+            file = file
+                .option(Parameter::key_value("map", "[v]"))
+                .option(Parameter::key_value("c:v", encoder));
+        }
+        if let (Some(_), Some(encoder)) = (audio_stream, container.get_default_encoder(&StreamKind::Audio)) {
+            graph += "[a]";
+            file = file
+                .option(Parameter::key_value("map", "[a]"))
+                .option(Parameter::key_value("c:a", encoder));
+            if !container.is_lossless() {
+                file = file.option(Parameter::key_value("b:a", "192k"));
+            }
+        }
 
-    let format = match params.container {
-        VideoContainer::MKV => "matroska",
-        VideoContainer::MP4 => "mp4",
-        VideoContainer::MP3 => todo!(),
-        VideoContainer::WEBM => todo!(),
+        // Nothing after the last range needs to be decoded
+        let last = ranges[ranges.len() - 1].1;
+        builder.inputs = vec![File::new(url).option(Parameter::key_value("t", last.to_string()))];
+        file.option(Parameter::key_value("filter_complex", graph))
     };
+    builder.outputs = vec![file];
+
+    builder.run_and_upload(&video.id).await?;
+    Ok(())
+}
+
+/// Frame rate kept by slow motion when the video doesn't tell its own
+const SPEED_DEFAULT_FPS: f64 = 30.0;
+const SPEED_DEFAULT_SAMPLE_RATE: u32 = 48000;
+
+pub async fn speed(video: &Video, params: &SpeedParameters) -> Result<(), error::Worker> {
+    let factor = params.speed_factor;
+    if factor <= 0.0 || !factor.is_finite() {
+        return Err(error::Encode::Speed(error::Speed::InvalidFactor)).context(error::EncodeSnafu)?;
+    }
+    if matches!(params.mode, SpeedMode::SlowMotion { .. }) && factor >= 1.0 {
+        return Err(error::Encode::Speed(error::Speed::NotSlower)).context(error::EncodeSnafu)?;
+    }
+
+    let source = Input::resolve(&video.url).await?;
+    let url = source.location()?;
+    let streams = get_streams(video).await?;
+    let video_stream = first_stream(&streams, &StreamKind::Video);
+    let audio_stream = first_stream(&streams, &StreamKind::Audio);
+    if video_stream.is_none() && audio_stream.is_none() {
+        return Err(error::Encode::Speed(error::Speed::NoStream)).context(error::EncodeSnafu)?;
+    }
+
+    let sample_rate = audio_stream.and_then(|s| s.sample_rate).unwrap_or(SPEED_DEFAULT_SAMPLE_RATE);
+    let frame_rate = video_stream.and_then(|s| s.frame_rate).filter(|f| *f > 0.0 && *f <= 120.0).unwrap_or(SPEED_DEFAULT_FPS);
+    let (video_filter, audio_filter) = speed_filters(params, sample_rate, frame_rate);
+
+    // The video timestamps are rescaled at demuxing so it can be copied,
+    // the audio is read again from a second input to keep its own timestamps
+    let rescale = params.mode == SpeedMode::AudioOnly && video_stream.is_some();
+    let mut input = File::new(url);
+    if rescale {
+        input = input.option(Parameter::key_value("itsscale", (1.0 / factor).to_string()));
+    }
+    let mut builder = FfmpegBuilder::new()
+        .option(Parameter::single("nostdin"))
+        .option(Parameter::single("y"))
+        .input(input);
+    if rescale {
+        builder = builder.input(File::new(url));
+    }
+
+    let mut file = File::new("pipe:1")
+        .option(Parameter::key_value("f", "mp4"))
+        .option(Parameter::key_value("movflags", "frag_keyframe+empty_moov"));
+    if let Some(s) = video_stream {
+        file = file.option(Parameter::key_value("map", format!("0:{}", s.id)));
+        file = match video_filter {
+            Some(filter) => file
+                .option(Parameter::key_value("filter:v", filter))
+                .option(Parameter::key_value("c:v", "libx264")),
+            None => {
+                let copy = matches!(&s.codec, Some(c) if VideoContainer::MP4.accepts_codec(&StreamKind::Video, c));
+                file.option(Parameter::key_value("c:v", if copy { "copy" } else { "libx264" }))
+            }
+        };
+    }
+    if let Some(s) = audio_stream {
+        file = file
+            .option(Parameter::key_value("map", format!("{}:{}", if rescale { 1 } else { 0 }, s.id)))
+            .option(Parameter::key_value("filter:a", audio_filter))
+            .option(Parameter::key_value("c:a", "aac"));
+    }
+
+    builder.output(file).run_and_upload(&video.id).await?;
+    Ok(())
+}
+
+/// Video and audio filters of a speed change, there is no video filter when the video is copied
+fn speed_filters(params: &SpeedParameters, sample_rate: u32, frame_rate: f64) -> (Option<String>, String) {
+    let factor = params.speed_factor;
+    let setpts = format!("setpts=PTS/{}", factor);
+    let atempo = utils::atempo_chain(factor);
+    // Played at another rate then resampled back, the pitch and tempo both follow
+    let rate = (sample_rate as f64 * factor).round().max(1.0) as u32;
+    let resample = format!("asetrate={},aresample={}", rate, sample_rate);
+    match params.mode {
+        SpeedMode::KeepPitch => (Some(setpts), atempo),
+        SpeedMode::Chipmunk => (Some(setpts), resample),
+        SpeedMode::PitchShift => (None, format!("{},{}", resample, utils::atempo_chain(sample_rate as f64 / rate as f64))),
+        SpeedMode::AudioOnly => (None, atempo),
+        SpeedMode::SlowMotion { interpolate: false } => (Some(format!("{},fps={}", setpts, frame_rate)), atempo),
+        SpeedMode::SlowMotion { interpolate: true } => {
+            (Some(format!("{},minterpolate=fps={}:mi_mode=mci", setpts, frame_rate)), atempo)
+        }
+    }
+}
+
+/// Keeps the first audio track of an mp4 output, only transcoding it when mp4 can't hold it
+fn map_first_audio<'a>(file: File<'a>, streams: &[MediaStream]) -> File<'a> {
+    match streams.iter().find(|s| s.kind == StreamKind::Audio) {
+        Some(audio) => {
+            let copy = matches!(&audio.codec, Some(c) if VideoContainer::MP4.accepts_codec(&StreamKind::Audio, c));
+            file.option(Parameter::key_value("map", format!("0:{}", audio.id)))
+                .option(Parameter::key_value("c:a", if copy { "copy" } else { "aac" }))
+        }
+        None => file,
+    }
+}
+
+/// Black borders of the video as `(x, y, width, height)` of the area to keep
+async fn detect_crop(url: &str) -> Result<(u32, u32, u32, u32), error::Worker> {
+    let builder = FfmpegBuilder::new()
+        .option(Parameter::single("nostdin"))
+        .option(Parameter::single("hide_banner"))
+        .input(File::new(url))
+        .output(File::new("-")
+            .option(Parameter::key_value("map", "0:v:0"))
+            .option(Parameter::key_value("filter:v", "cropdetect=round=2"))
+            .option(Parameter::key_value("f", "null")));
+
+    let stderr = run_analysis(builder).await?;
+    utils::parse_cropdetect(&stderr).ok_or(error::Encode::Transform(error::Transform::CropDetect)).context(error::EncodeSnafu)
+}
+
+/// Colors go into the filtergraph, anything but a name or hex code could add filters
+fn is_valid_color(color: &str) -> bool {
+    !color.is_empty() && color.len() <= 32 && color.chars().all(|c| c.is_ascii_alphanumeric() || c == '#')
+}
+
+pub async fn transform(video: &Video, params: &TransformParameters) -> Result<(), error::Worker> {
+    let source = Input::resolve(&video.url).await?;
+    let url = source.location()?;
+
+    let streams = get_streams(video).await?;
+    let (mut width, mut height) = streams
+        .iter()
+        .find(|s| s.kind == StreamKind::Video)
+        // Frames are rotated before going through the filters
+        .and_then(|s| s.get_display_size())
+        .ok_or(error::Encode::Transform(error::Transform::NoVideoStream))
+        .context(error::EncodeSnafu)?;
+
+    let mut filters = Vec::new();
+    if let Some(crop) = &params.crop {
+        let (x, y, w, h) = match crop {
+            Crop::Rect { x, y, width, height } => (*x, *y, *width, *height),
+            Crop::Auto => detect_crop(url).await?,
+        };
+        // libx264 needs even sizes
+        let (w, h) = (w & !1, h & !1);
+        if w == 0 || h == 0 || x as u64 + w as u64 > width as u64 || y as u64 + h as u64 > height as u64 {
+            return Err(error::Encode::Transform(error::Transform::InvalidCrop)).context(error::EncodeSnafu)?;
+        }
+        filters.push(format!("crop={w}:{h}:{x}:{y}"));
+        (width, height) = (w, h);
+    }
+    if let Some(scale) = &params.scale {
+        (width, height) = scale
+            .get_size(width, height)
+            .ok_or(error::Encode::Transform(error::Transform::InvalidScale))
+            .context(error::EncodeSnafu)?;
+        filters.push(format!("scale={width}:{height}"));
+    }
+    if filters.is_empty() {
+        filters.push("null".to_owned());
+    }
+    let chain = filters.join(",");
+
+    let graph = match &params.pad {
+        None => format!("[0:v:0]{chain},setsar=1[v]"),
+        Some(pad) => {
+            let ((w, h), scaled) = pad.aspect_ratio.fit(width, height);
+            let chain = match scaled == (width, height) {
+                true => chain,
+                false => format!("{chain},scale={}:{}", scaled.0, scaled.1),
+            };
+            match &pad.fill {
+                PadFill::Color(color) => {
+                    if !is_valid_color(color) {
+                        return Err(error::Encode::Transform(error::Transform::InvalidColor(color.to_owned()))).context(error::EncodeSnafu)?;
+                    }
+                    format!("[0:v:0]{chain},pad={w}:{h}:(ow-iw)/2:(oh-ih)/2:color={color},setsar=1[v]")
+                }
+                // The video over an enlarged blurred copy of itself
+                PadFill::Blur => format!(
+                    "[0:v:0]{chain},split[fg][bg];\
+                    [bg]scale={w}:{h}:force_original_aspect_ratio=increase,crop={w}:{h},gblur=sigma=20[blurred];\
+                    [blurred][fg]overlay=(W-w)/2:(H-h)/2,setsar=1[v]"
+                ),
+            }
+        }
+    };
+
+    let file = File::new("pipe:1").option(Parameter::key_value("f", "mp4"))
+    .option(Parameter::key_value("movflags", "frag_keyframe+empty_moov"))
+    .option(Parameter::key_value("filter_complex", graph))
+    .option(Parameter::key_value("map", "[v]"))
+    .option(Parameter::key_value("c:v", "libx264"));
+
+    let file = map_first_audio(file, &streams);
 
     let mut builder = FfmpegBuilder::default(url);
-    let file = File::new("pipe:1").option(Parameter::key_value("f", format)).option(Parameter::key_value("movflags", "frag_keyframe+empty_moov"))
-    .option(Parameter::key_value("c:v", "copy")).option(Parameter::key_value("c:a", "copy"));
     builder.outputs = vec![file];
 
     builder.run_and_upload(&video.id).await?;
     Ok(())
 }
 
-pub async fn cut(video: &Video, params: &CutParameters) -> Result<(), error::Worker> {
-    let url = match &video.url {
-        VideoURI::Path(u) => u,
-        VideoURI::Url(u) => u,
+pub async fn orient(video: &Video, params: &OrientParameters) -> Result<(), error::Worker> {
+    let source = Input::resolve(&video.url).await?;
+    let url = source.location()?;
+
+    let streams = get_streams(video).await?;
+    let stream = streams
+        .iter()
+        .find(|s| s.kind == StreamKind::Video)
+        .ok_or(error::Encode::Transform(error::Transform::NoVideoStream))
+        .context(error::EncodeSnafu)?;
+
+    let mut builder = FfmpegBuilder::default(url);
+    let file = match (params.get_metadata_container(video.get_container()), params.orientation) {
+        // Only the rotation tag changes, the streams are copied as is
+        (Some(container), Orientation::Rotate(rotation)) => {
+            let degrees = (stream.rotation.unwrap_or(0) + rotation.get_degrees()) % 360;
+            File::new("pipe:1").option(Parameter::key_value("f", container.get_format()))
+            .option(Parameter::key_value("movflags", "frag_keyframe+empty_moov"))
+            .option(Parameter::key_value("map", format!("0:{}", stream.id)))
+            .option(Parameter::key_value("map", "0:a?"))
+            .option(Parameter::key_value("c", "copy"))
+            .option(Parameter::key_value("metadata:s:v:0", format!("rotate={degrees}")))
+        }
+        // ffmpeg applies the current rotation while decoding, the filter comes after it
+        (_, orientation) => {
+            let filter = match orientation {
+                Orientation::Rotate(Rotation::Clockwise) => "transpose=clock",
+                Orientation::Rotate(Rotation::UpsideDown) => "hflip,vflip",
+                Orientation::Rotate(Rotation::CounterClockwise) => "transpose=cclock",
+                Orientation::FlipHorizontal => "hflip",
+                Orientation::FlipVertical => "vflip",
+                Orientation::Bake => "null",
+            };
+            let file = File::new("pipe:1").option(Parameter::key_value("f", "mp4"))
+            .option(Parameter::key_value("movflags", "frag_keyframe+empty_moov"))
+            .option(Parameter::key_value("map", format!("0:{}", stream.id)))
+            .option(Parameter::key_value("filter:v", filter))
+            .option(Parameter::key_value("c:v", "libx264"))
+            .option(Parameter::key_value("metadata:s:v:0", "rotate=0"));
+            map_first_audio(file, &streams)
+        }
     };
+    builder.outputs = vec![file];
+
+    builder.run_and_upload(&video.id).await?;
+    Ok(())
+}
+
+/// Attempts at reaching the target size of an animation
+const ANIMATION_MAX_ATTEMPTS: usize = 6;
+const ANIMATION_MIN_FPS: u32 = 8;
+const ANIMATION_MIN_WIDTH: u32 = 64;
+
+pub async fn animation(video: &Video, params: &AnimationParameters) -> Result<(), error::Worker> {
+    let source = Input::resolve(&video.url).await?;
+    let url = source.location()?;
+
+    let streams = get_streams(video).await?;
+    let (source_width, _) = streams
+        .iter()
+        .find(|s| s.kind == StreamKind::Video)
+        .and_then(|s| s.get_display_size())
+        .ok_or(error::Encode::Animation(error::Animation::NoVideoStream))
+        .context(error::EncodeSnafu)?;
+
+    let dir = get_working_dir(&video.id).context(error::IoSnafu)?;
+    let output = dir.join(format!("animation.{}", params.format.get_file_extension()));
+
+    let mut fps = params.fps.max(1);
+    let mut width = params.width.unwrap_or(source_width).clamp(ANIMATION_MIN_WIDTH, source_width.max(ANIMATION_MIN_WIDTH));
+    for attempt in 1..=ANIMATION_MAX_ATTEMPTS {
+        encode_animation(url, params, fps, width, &dir, &output).await?;
+
+        let size = tokio::fs::metadata(&output).await.context(error::IoSnafu)?.len();
+        let target = match params.target_size {
+            Some(t) if size > t => t,
+            _ => break,
+        };
+        if attempt == ANIMATION_MAX_ATTEMPTS || (fps <= ANIMATION_MIN_FPS && width == ANIMATION_MIN_WIDTH) {
+            return Err(error::Encode::Animation(error::Animation::TargetSizeTooSmall)).context(error::EncodeSnafu)?;
+        }
+
+        // The size is roughly proportional to the fps and the area, the reduction is spread
+        // over the three with a margin as palettes and compression don't scale linearly
+        let factor = (target as f64 / size as f64).powf(1.0 / 3.0).clamp(0.5, 0.9);
+        fps = ((fps as f64 * factor) as u32).max(ANIMATION_MIN_FPS.min(fps));
+        width = ((width as f64 * factor) as u32).max(ANIMATION_MIN_WIDTH);
+    }
+
+    // ffmpeg can't decode animated webp, it would fail the probe
+    upload_file(&output, &storage::output_key(&video.id), params.format != AnimationFormat::WebP).await
+}
+
+async fn encode_animation(url: &str, params: &AnimationParameters, fps: u32, width: u32, dir: &Path, output: &Path) -> Result<(), error::Worker> {
+    let output = output.to_str().ok_or(error::Worker::Message { msg: "output path str conversion error".to_owned() })?;
+    let palette = dir.join("palette.png");
+    let palette = palette.to_str().ok_or(error::Worker::Message { msg: "palette path str conversion error".to_owned() })?;
+    let filters = format!("fps={fps},scale={width}:-1:flags=lanczos");
+
     let mut builder = FfmpegBuilder::default(url);
+    builder.stdout = Stdio::null();
+
+    let file = File::new(output)
+        .option(Parameter::single("an"))
+        .option(Parameter::key_value("f", params.format.get_format()));
+    let file = match params.format {
+        AnimationFormat::Gif => {
+            // First pass finds the best 256 colors palette for the whole clip
+            let mut palette_builder = FfmpegBuilder::default(url);
+            palette_builder.stdout = Stdio::null();
+            palette_builder.outputs = vec![File::new(palette)
+                .option(Parameter::key_value("map", "0:v:0"))
+                .option(Parameter::key_value("filter:v", format!("{filters},palettegen=stats_mode=diff")))];
+            run_to_completion(palette_builder).await?;
 
-    if let Some(time) = params.start {
-        builder = builder.option(Parameter::key_value("ss", time.as_secs_f64().to_string()));
+            // gifs are played once more than their loop count, -1 plays them once
+            let loops = match params.loops {
+                0 => 0,
+                1 => -1,
+                n => n as i64 - 1,
+            };
+            builder = builder.input(File::new(palette));
+            file.option(Parameter::key_value("filter_complex", format!(
+                "[0:v:0]{filters}[x];[x][1:v]paletteuse=dither={}:diff_mode=rectangle",
+                params.dither.get_name()
+            )))
+            .option(Parameter::key_value("loop", loops.to_string()))
+        }
+        AnimationFormat::WebP => file
+            .option(Parameter::key_value("map", "0:v:0"))
+            .option(Parameter::key_value("filter:v", filters))
+            .option(Parameter::key_value("c:v", "libwebp"))
+            .option(Parameter::key_value("lossless", "0"))
+            .option(Parameter::key_value("q:v", "75"))
+            .option(Parameter::key_value("loop", params.loops.to_string())),
+        AnimationFormat::APng => file
+            .option(Parameter::key_value("map", "0:v:0"))
+            .option(Parameter::key_value("filter:v", filters))
+            .option(Parameter::key_value("c:v", "apng"))
+            .option(Parameter::key_value("plays", params.loops.to_string())),
+    };
+    builder.outputs = vec![file];
+
+    run_to_completion(builder).await
+}
+
+pub async fn extract_audio(video: &Video, params: &ExtractAudioParameters) -> Result<(), error::Worker> {
+    let source = Input::resolve(&video.url).await?;
+    let url = source.location()?;
+
+    let streams = get_streams(video).await?;
+    let stream = streams
+        .iter()
+        .find(|s| s.id == params.stream && s.kind == StreamKind::Audio)
+        .ok_or(error::Encode::ExtractAudio(error::ExtractAudio::StreamNotFound(params.stream)))
+        .context(error::EncodeSnafu)?;
+
+    let container = params.container;
+    if !VideoContainer::AUDIO.contains(&container) {
+        return Err(error::Encode::ExtractAudio(error::ExtractAudio::UnsupportedContainer)).context(error::EncodeSnafu)?;
     }
-    if let Some(time) = params.end {
-        builder = builder.option(Parameter::key_value("to", time.as_secs_f64().to_string()));
+
+    let mut file = File::new("pipe:1").option(Parameter::key_value("f", container.get_format()));
+    if container.is_mp4_family() {
+        file = file.option(Parameter::key_value("movflags", "frag_keyframe+empty_moov"));
     }
-    
+    file = file.option(Parameter::key_value("map", format!("0:{}", stream.id)));
+
+    // Copy the stream as is when the container can hold its codec
+    file = match &stream.codec {
+        Some(codec) if container.accepts_codec(&StreamKind::Audio, codec) => file.option(Parameter::key_value("c:a", "copy")),
+        _ => {
+            let encoder = container
+                .get_default_encoder(&StreamKind::Audio)
+                .ok_or(error::Encode::ExtractAudio(error::ExtractAudio::UnsupportedContainer))
+                .context(error::EncodeSnafu)?;
+            file = file.option(Parameter::key_value("c:a", encoder));
+            match params.bit_rate {
+                Some(bit_rate) if !container.is_lossless() => file.option(Parameter::key_value("b:a", bit_rate.to_string())),
+                _ => file,
+            }
+        }
+    };
+
+    let mut builder = FfmpegBuilder::default(url);
+    builder.outputs = vec![file];
+
     builder.run_and_upload(&video.id).await?;
     Ok(())
 }
 
-pub async fn speed(video: &Video, params: &SpeedParameters) -> Result<(), error::Worker> {
-    let url = match &video.url {
-        VideoURI::Path(u) => u,
-        VideoURI::Url(u) => u,
+/// Sample rate of the normalized audio, loudnorm upsamples to 192kHz
+const LOUDNESS_SAMPLE_RATE: &str = "48000";
+
+/// Measures the loudness of the audio stream `stream` with a first loudnorm pass
+async fn measure_loudness(url: &str, stream: usize, target: &LoudnessTarget) -> Result<utils::LoudnessMeasure, error::Worker> {
+    let builder = FfmpegBuilder::new()
+        .option(Parameter::single("nostdin"))
+        .option(Parameter::single("hide_banner"))
+        .input(File::new(url))
+        .output(File::new("-")
+            .option(Parameter::key_value("map", format!("0:{stream}")))
+            .option(Parameter::key_value("filter:a", format!(
+                "loudnorm=I={}:TP={}:LRA={}:print_format=json",
+                target.integrated, target.true_peak, target.range
+            )))
+            .option(Parameter::key_value("f", "null")));
+
+    let stderr = run_analysis(builder).await?;
+    let measure = utils::parse_loudnorm(&stderr)
+        .ok_or(error::Encode::Loudness(error::Loudness::Measurement))
+        .context(error::EncodeSnafu)?;
+    // Silence measures -inf, there is nothing to normalize
+    if !measure.integrated.is_finite() || !measure.true_peak.is_finite() {
+        return Err(error::Encode::Loudness(error::Loudness::Silent)).context(error::EncodeSnafu)?;
+    }
+    Ok(measure)
+}
+
+pub async fn loudness(video: &Video, params: &LoudnessParameters) -> Result<(), error::Worker> {
+    let source = Input::resolve(&video.url).await?;
+    let url = source.location()?;
+
+    let streams = get_streams(video).await?;
+    let audio = streams
+        .iter()
+        .find(|s| s.kind == StreamKind::Audio)
+        .ok_or(error::Encode::Loudness(error::Loudness::NoAudioStream))
+        .context(error::EncodeSnafu)?;
+
+    let filter = match &params.adjustment {
+        VolumeAdjustment::Normalize(target) => {
+            let measure = measure_loudness(url, audio.id, target).await?;
+            // Linear mode applies a single gain, keeping the dynamics of the input
+            format!(
+                "loudnorm=I={}:TP={}:LRA={}:measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true,aresample={LOUDNESS_SAMPLE_RATE}",
+                target.integrated, target.true_peak, target.range,
+                measure.integrated, measure.true_peak, measure.range, measure.threshold, measure.offset
+            )
+        }
+        VolumeAdjustment::Gain(gain) => format!("volume={gain}dB"),
+        VolumeAdjustment::Mute => "volume=0".to_owned(),
     };
+
+    let container = VideoContainer::get_reencode_container(video.get_container());
+
+    let mut file = File::new("pipe:1").option(Parameter::key_value("f", container.get_format()));
+    if container.is_mp4_family() {
+        file = file.option(Parameter::key_value("movflags", "frag_keyframe+empty_moov"));
+    }
+
+    // The video is kept as is when the container can hold it
+    if let Some(video_stream) = streams.iter().find(|s| s.kind == StreamKind::Video) {
+        let encoder = match &video_stream.codec {
+            Some(c) if container.accepts_codec(&StreamKind::Video, c) => Some("copy"),
+            _ => container.get_default_encoder(&StreamKind::Video),
+        };
+        if let Some(encoder) = encoder {
+            file = file
+                .option(Parameter::key_value("map", format!("0:{}", video_stream.id)))
+                .option(Parameter::key_value("c:v", encoder));
+        }
+    }
+
+    let encoder = container
+        .get_default_encoder(&StreamKind::Audio)
+        .ok_or(error::Encode::Loudness(error::Loudness::NoAudioStream))
+        .context(error::EncodeSnafu)?;
+    file = file
+        .option(Parameter::key_value("map", format!("0:{}", audio.id)))
+        .option(Parameter::key_value("filter:a", filter))
+        .option(Parameter::key_value("c:a", encoder));
+    if !container.is_lossless() {
+        file = file.option(Parameter::key_value("b:a", "192k"));
+    }
+
     let mut builder = FfmpegBuilder::default(url);
+    builder.outputs = vec![file];
 
-    let mut atempo = "".to_owned();
-    let mut a = params.speed_factor;
-    let mut i = 0;
-    if a > 2.0 {
-        while a > 2.0 {
-            i += 1;
-            atempo += "atempo=2,";
-            a /= 2.0;
-        }
-        atempo += &format!("atempo={}", a);
-    } else if a < 0.5 {
-        while a < 0.5 {
-            i += 1;
-            atempo += "atempo=0.5,";
-            a *= 2.0;
-        }
-        atempo += &format!("atempo={}", a);
-        dbg!(&atempo);
-    } else {
-        atempo += &format!("atempo={}", a);
+    builder.run_and_upload(&video.id).await?;
+    Ok(())
+}
+
+/// Ranges of dead air in the input, sorted by start
+pub async fn detect_dead_air(video: &Video, params: &TrimParameters) -> Result<Vec<DeadAir>, error::Worker> {
+    let source = Input::resolve(&video.url).await?;
+    let url = source.location()?;
+
+    let streams = get_streams(video).await?;
+    let duration = container_duration(&streams);
+    let video_stream = first_stream(&streams, &StreamKind::Video);
+    let audio_stream = first_stream(&streams, &StreamKind::Audio);
+
+    let mut video_filters = Vec::new();
+    if let (Some(d), Some(_)) = (params.black, video_stream) {
+        video_filters.push(format!("blackdetect=d={}:pix_th={}", d.min_duration.as_secs_f64(), d.threshold));
     }
-        
+    if let (Some(d), Some(_)) = (params.freeze, video_stream) {
+        video_filters.push(format!("freezedetect=n={}dB:d={}", d.threshold, d.min_duration.as_secs_f64()));
+    }
+    let silence = match (params.silence, audio_stream) {
+        (Some(d), Some(_)) => Some(format!("silencedetect=n={}dB:d={}", d.threshold, d.min_duration.as_secs_f64())),
+        _ => None,
+    };
+
+    let mut file = File::new("-").option(Parameter::key_value("f", "null"));
+    let mut analysed = false;
+    if let Some(s) = video_stream.filter(|_| !video_filters.is_empty()) {
+        file = file
+            .option(Parameter::key_value("map", format!("0:{}", s.id)))
+            .option(Parameter::key_value("filter:v", video_filters.join(",")));
+        analysed = true;
+    }
+    if let (Some(s), Some(filter)) = (audio_stream, silence) {
+        file = file
+            .option(Parameter::key_value("map", format!("0:{}", s.id)))
+            .option(Parameter::key_value("filter:a", filter));
+        analysed = true;
+    }
+    if !analysed {
+        return Err(error::Encode::Trim(error::Trim::NoStream)).context(error::EncodeSnafu)?;
+    }
+
+    let builder = FfmpegBuilder::new()
+        .option(Parameter::single("nostdin"))
+        .option(Parameter::single("hide_banner"))
+        .input(File::new(url))
+        .output(file);
+    let stderr = run_analysis(builder).await?;
+
+    let mut found = Vec::new();
+    let keys = [
+        (DeadAirKind::Silence, "silence_start:", "silence_end:"),
+        (DeadAirKind::Black, "black_start:", "black_end:"),
+        (DeadAirKind::Freeze, "freeze_start:", "freeze_end:"),
+    ];
+    for (kind, start_key, end_key) in keys {
+        for (start, end) in utils::parse_detected_ranges(&stderr, start_key, end_key) {
+            let start = start.max(0.0);
+            found.push(DeadAir {
+                kind,
+                start: std::time::Duration::from_secs_f64(start),
+                end: std::time::Duration::from_secs_f64(end.unwrap_or(duration).max(start)),
+            });
+        }
+    }
+    found.sort_by_key(|d| d.start);
+    Ok(found)
+}
+
+/// Removes the dead air from the input, audio and video together
+pub async fn trim(video: &Video, params: &TrimParameters) -> Result<(), error::Worker> {
+    let dead_air = detect_dead_air(video, params).await?;
+    if dead_air.is_empty() {
+        return Err(error::Encode::Trim(error::Trim::NothingDetected)).context(error::EncodeSnafu)?;
+    }
+
+    let segments = dead_air
+        .iter()
+        .map(|d| CutSegment { start: Some(d.start), end: Some(d.end) })
+        .collect();
+    cut(video, &CutParameters { segments, remove: true, mode: CutMode::Reencode }).await
+}
+
+/// Shortest scene, changes closer to the previous one or to the end are ignored
+const SCENE_MIN_LENGTH: f64 = 1.0;
+
+/// Timestamps the scenes after the first one start at, in seconds
+pub async fn detect_scenes(video: &Video, threshold: f64) -> Result<Vec<f64>, error::Worker> {
+    let source = Input::resolve(&video.url).await?;
+    let url = source.location()?;
+
+    let streams = get_streams(video).await?;
+    let duration = container_duration(&streams);
+    let stream = first_stream(&streams, &StreamKind::Video)
+        .ok_or(error::Encode::Scene(error::Scene::NoVideoStream))
+        .context(error::EncodeSnafu)?;
+
+    // Scores barely change on smaller frames, which are much faster to compare
+    let builder = FfmpegBuilder::new()
+        .option(Parameter::single("nostdin"))
+        .option(Parameter::single("hide_banner"))
+        .input(File::new(url))
+        .output(File::new("-")
+            .option(Parameter::key_value("map", format!("0:{}", stream.id)))
+            .option(Parameter::key_value("filter:v", format!("settb=AVTB,scale=320:-2,select=gt(scene\\,{threshold}),showinfo")))
+            .option(Parameter::key_value("f", "null")));
+    let stderr = run_analysis(builder).await?;
+
+    let mut scenes = utils::drop_close(&utils::parse_frame_times(&stderr), SCENE_MIN_LENGTH);
+    scenes.retain(|t| duration - t >= SCENE_MIN_LENGTH);
+    Ok(scenes)
+}
+
+/// Adds a chapter per scene or splits the input at the scene changes, returns the parts of a split
+pub async fn scenes(video: &Video, params: &SceneParameters) -> Result<Vec<job::Output>, error::Worker> {
+    let scenes = detect_scenes(video, params.threshold).await?;
+    if scenes.is_empty() {
+        return Err(error::Encode::Scene(error::Scene::NothingDetected)).context(error::EncodeSnafu)?;
+    }
+    if params.action == SceneAction::Split {
+        let timestamps = scenes.iter().map(|t| std::time::Duration::from_secs_f64(*t)).collect();
+        return split(video, &SplitParameters { mode: SplitMode::Timestamps(timestamps) }).await;
+    }
+
+    let source = Input::resolve(&video.url).await?;
+    let url = source.location()?;
+    let streams = get_streams(video).await?;
+    let duration = container_duration(&streams);
+
+    let mut bounds = vec![0.0];
+    bounds.extend(scenes);
+    bounds.push(duration);
+    let mut chapters = ";FFMETADATA1\n".to_owned();
+    for (i, range) in bounds.windows(2).enumerate() {
+        chapters += &format!(
+            "[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle=Chapter {}\n",
+            (range[0] * 1000.0).round() as u64,
+            (range[1] * 1000.0).round() as u64,
+            i + 1
+        );
+    }
+    let dir = get_working_dir(&video.id).context(error::IoSnafu)?;
+    let metadata = job_file(&dir, "chapters.txt")?;
+    tokio::fs::write(&metadata, chapters).await.context(error::IoSnafu)?;
+
+    // Chapters are written in the header, the output goes to a file
+    let container = params.get_container(video.get_container());
+    let output = job_file(&dir, &format!("chapters.{}", container.get_file_extension()))?;
+    let builder = FfmpegBuilder::new()
+        .option(Parameter::single("nostdin"))
+        .option(Parameter::single("y"))
+        .input(File::new(url))
+        .input(File::new(&metadata).option(Parameter::key_value("f", "ffmetadata")))
+        .output(File::new(&output)
+            .option(Parameter::key_value("map", "0"))
+            .option(Parameter::key_value("map_chapters", "1"))
+            .option(Parameter::key_value("c", "copy"))
+            .option(Parameter::key_value("f", container.get_format())));
+    run_to_completion(builder).await?;
+
+    upload_file(Path::new(&output), &storage::output_key(&video.id), true).await?;
+    Ok(Vec::new())
+}
+
+/// Share of the size limit a part is expected to use, they end on the keyframe after their length
+const SPLIT_SIZE_MARGIN: f64 = 0.9;
+const SPLIT_MAX_ATTEMPTS: usize = 4;
+
+/// Parts written by the segment muxer, in order
+fn split_parts(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    (0..)
+        .map(|i| dir.join(format!("part{i:03}.{extension}")))
+        .take_while(|part| part.exists())
+        .collect()
+}
+
+/// Splits the input into parts stored under [`storage::output_part_key`], returns them in order
+pub async fn split(video: &Video, params: &SplitParameters) -> Result<Vec<job::Output>, error::Worker> {
+    let source = Input::resolve(&video.url).await?;
+    let url = source.location()?;
+
+    let streams = get_streams(video).await?;
+    let duration = container_duration(&streams);
+
+    let container = params.get_container(video.get_container());
+    let mapped: Vec<&MediaStream> = [StreamKind::Video, StreamKind::Audio]
+        .iter()
+        .filter_map(|kind| first_stream(&streams, kind))
+        .filter(|s| matches!(&s.codec, Some(c) if container.accepts_codec(&s.kind, c)))
+        .collect();
+    if mapped.is_empty() {
+        return Err(error::Encode::Split(error::Split::NoStream)).context(error::EncodeSnafu)?;
+    }
+
+    let mut length = match &params.mode {
+        SplitMode::Parts(count) => duration / (*count).max(1) as f64,
+        SplitMode::Length(length) => length.as_secs_f64(),
+        SplitMode::Size(limit) => {
+            let size = match tokio::fs::metadata(url).await {
+                Ok(metadata) => metadata.len(),
+                Err(_) => streams.iter().filter_map(|s| s.bit_rate).sum::<u64>() * duration as u64 / 8,
+            };
+            duration * (*limit as f64 / size.max(1) as f64) * SPLIT_SIZE_MARGIN
+        }
+        SplitMode::Timestamps(_) => 0.0,
+    };
+
+    let dir = get_working_dir(&video.id).context(error::IoSnafu)?;
+    let extension = container.get_file_extension();
+    let pattern = job_file(&dir, &format!("part%03d.{extension}"))?;
+
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let times = match &params.mode {
+            SplitMode::Timestamps(timestamps) => {
+                let mut times: Vec<f64> = timestamps.iter().map(|t| t.as_secs_f64()).filter(|t| *t > 0.0 && *t < duration).collect();
+                times.sort_by(|a, b| a.total_cmp(b));
+                times.dedup();
+                times
+            }
+            _ => utils::split_every(length, duration),
+        };
+        if times.len() >= SplitParameters::MAX_PARTS {
+            return Err(error::Encode::Split(error::Split::TooManyParts(SplitParameters::MAX_PARTS))).context(error::EncodeSnafu)?;
+        }
+
+        // Parts of a previous attempt would be counted otherwise
+        for part in split_parts(&dir, &extension) {
+            tokio::fs::remove_file(part).await.context(error::IoSnafu)?;
+        }
+
+        let mut file = File::new(&pattern)
+            .option(Parameter::key_value("c", "copy"))
+            .option(Parameter::key_value("f", "segment"))
+            .option(Parameter::key_value("segment_format", container.get_format()))
+            .option(Parameter::key_value("reset_timestamps", "1"));
+        file = match times.is_empty() {
+            // The muxer cuts every 2 seconds by default
+            true => file.option(Parameter::key_value("segment_time", (duration.ceil() + 1.0).to_string())),
+            false => file.option(Parameter::key_value(
+                "segment_times",
+                times.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(","),
+            )),
+        };
+        if container.is_mp4_family() {
+            file = file.option(Parameter::key_value("segment_format_options", "movflags=+faststart"));
+        }
+        for s in mapped.iter() {
+            file = file.option(Parameter::key_value("map", format!("0:{}", s.id)));
+        }
+        let builder = FfmpegBuilder::new()
+            .option(Parameter::single("nostdin"))
+            .option(Parameter::single("y"))
+            .input(File::new(url))
+            .output(file);
+        run_to_completion(builder).await?;
+
+        let parts = split_parts(&dir, &extension);
+
+        // Parts over the limit are made shorter, as long as there are attempts left,
+        // the bot sends the ones still over it as links and says so
+        if let SplitMode::Size(limit) = &params.mode {
+            let mut fits = true;
+            for part in parts.iter() {
+                fits &= tokio::fs::metadata(part).await.context(error::IoSnafu)?.len() <= *limit;
+            }
+            if !fits && attempt < SPLIT_MAX_ATTEMPTS {
+                length *= 0.75;
+                continue;
+            }
+        }
+
+        let stem = Path::new(&video.filename).file_stem().and_then(|s| s.to_str()).unwrap_or("video");
+        let mut outputs = Vec::with_capacity(parts.len());
+        for (i, part) in parts.iter().enumerate() {
+            let key = storage::output_part_key(&video.id, i);
+            upload_file(part, &key, true).await?;
+            let size = tokio::fs::metadata(part).await.context(error::IoSnafu)?.len();
+            outputs.push(job::Output::new(key, format!("{stem}-{}.{extension}", i + 1), size));
+        }
+        return Ok(outputs);
+    }
+}
+
+/// Output frame rate when the first video doesn't have one
+const CONCAT_DEFAULT_FPS: f64 = 30.0;
+const CONCAT_SAMPLE_RATE: u32 = 48000;
+
+/// Duration of the input in seconds, every stream carries the one of the container
+fn container_duration(streams: &[MediaStream]) -> f64 {
+    streams.first().map(|s| s.duration.max(0) as f64 / 1_000_000.0).unwrap_or(0.0)
+}
+
+fn first_stream<'a>(streams: &'a [MediaStream], kind: &StreamKind) -> Option<&'a MediaStream> {
+    streams.iter().find(|s| &s.kind == kind)
+}
+
+/// Whether the inputs can be joined by the concat demuxer without re-encoding,
+/// their streams must share every parameter and fit in mp4
+fn can_concat_copy(inputs: &[Vec<MediaStream>]) -> bool {
+    let Some(reference) = inputs.first() else { return false };
+    let same = |kind: &StreamKind| {
+        let reference = first_stream(reference, kind);
+        inputs.iter().all(|streams| match (first_stream(streams, kind), reference) {
+            (None, None) => true,
+            (Some(a), Some(b)) => {
+                matches!(&a.codec, Some(c) if VideoContainer::MP4.accepts_codec(kind, c))
+                    && a.codec == b.codec
+                    && a.width == b.width
+                    && a.height == b.height
+                    && a.rotation == b.rotation
+                    && a.channels == b.channels
+                    && a.sample_rate == b.sample_rate
+                    && match (a.frame_rate, b.frame_rate) {
+                        (Some(x), Some(y)) => (x - y).abs() < 0.01,
+                        (x, y) => x.is_none() && y.is_none(),
+                    }
+            }
+            _ => false,
+        })
+    };
+    same(&StreamKind::Video) && same(&StreamKind::Audio)
+}
+
+/// Escapes a path or url for a concat demuxer list
+fn concat_list_entry(location: &str) -> String {
+    format!("file '{}'\n", location.replace('\'', "'\\''"))
+}
+
+/// Chains the normalized `[v{i}]` and `[a{i}]` inputs two by two with xfade and acrossfade,
+/// each transition overlaps the end of a clip with the start of the next
+fn transition_graph(transition: &Transition, durations: &[f64], has_video: bool, has_audio: bool) -> String {
+    // A transition can't be longer than half of the shortest clip
+    let shortest = durations.iter().copied().fold(f64::INFINITY, f64::min);
+    let length = transition.duration.as_secs_f64().min(shortest / 2.0).max(0.01);
+
+    let mut chains = Vec::new();
+    let (mut last_video, mut last_audio) = ("v0".to_owned(), "a0".to_owned());
+    let mut offset = 0.0;
+    for i in 1..durations.len() {
+        offset += durations[i - 1] - length;
+        let last = i == durations.len() - 1;
+        if has_video {
+            let output = if last { "v".to_owned() } else { format!("vx{i}") };
+            chains.push(format!(
+                "[{last_video}][v{i}]xfade=transition={}:duration={length}:offset={offset}[{output}]",
+                transition.kind.get_filter_name()
+            ));
+            last_video = output;
+        }
+        if has_audio {
+            let output = if last { "a".to_owned() } else { format!("ax{i}") };
+            chains.push(format!("[{last_audio}][a{i}]acrossfade=d={length}[{output}]"));
+            last_audio = output;
+        }
+    }
+    chains.join(";")
+}
+
+pub async fn concat(video: &Video, params: &ConcatParameters) -> Result<(), error::Worker> {
+    if params.videos.len() < 2 {
+        return Err(error::Encode::Concat(error::Concat::NotEnoughInputs)).context(error::EncodeSnafu)?;
+    }
+
+    let mut sources = Vec::with_capacity(params.videos.len());
+    let mut inputs = Vec::with_capacity(params.videos.len());
+    for url in params.videos.iter() {
+        sources.push(Input::resolve(url).await?);
+        inputs.push(get_streams(&Video { url: url.to_owned(), ..video.to_owned() }).await?);
+    }
+    let locations = sources.iter().map(|s| s.location()).collect::<Result<Vec<_>, _>>()?;
+
+    let dir = get_working_dir(&video.id).context(error::IoSnafu)?;
+    let list = dir.join("concat.txt");
+    let list = list.to_str().ok_or(error::Worker::Message { msg: "concat list path str conversion error".to_owned() })?;
+
+    let file = File::new("pipe:1").option(Parameter::key_value("f", "mp4"))
+    .option(Parameter::key_value("movflags", "frag_keyframe+empty_moov"));
+
+    let mut builder = FfmpegBuilder::default(locations[0]);
+    builder.inputs.clear();
+
+    // Transitions blend frames, they always go through the filters
+    let file = if params.transition.is_none() && can_concat_copy(&inputs) {
+        // Identical streams are joined packet by packet
+        let entries: String = locations.iter().map(|l| concat_list_entry(l)).collect();
+        tokio::fs::write(list, entries).await.context(error::IoSnafu)?;
+
+        builder = builder.input(File::new(list)
+            .option(Parameter::key_value("f", "concat"))
+            .option(Parameter::key_value("safe", "0"))
+            .option(Parameter::key_value("protocol_whitelist", "file,http,https,tcp,tls")));
+        file.option(Parameter::key_value("map", "0:v:0?"))
+            .option(Parameter::key_value("map", "0:a:0?"))
+            .option(Parameter::key_value("c", "copy"))
+    } else {
+        // Every input is brought to the size, frame rate and sample rate of the first video,
+        // missing streams are replaced by black frames or silence
+        let first_video = inputs.iter().find_map(|s| first_stream(s, &StreamKind::Video));
+        let has_video = first_video.is_some();
+        let has_audio = inputs.iter().any(|s| first_stream(s, &StreamKind::Audio).is_some());
+        if !has_video && !has_audio {
+            return Err(error::Encode::Concat(error::Concat::NoStream)).context(error::EncodeSnafu)?;
+        }
+        let (width, height) = first_video
+            .and_then(|s| s.get_display_size())
+            .map(|(w, h)| (w + w % 2, h + h % 2))
+            .unwrap_or((1280, 720));
+        let fps = first_video.and_then(|s| s.frame_rate).filter(|f| *f > 0.0 && *f <= 120.0).unwrap_or(CONCAT_DEFAULT_FPS);
+
+        let durations: Vec<f64> = inputs
+            .iter()
+            .map(|streams| container_duration(streams))
+            .collect();
+
+        let mut graph = String::new();
+        let mut segments = String::new();
+        for (i, (streams, duration)) in inputs.iter().zip(durations.iter()).enumerate() {
+            if has_video {
+                match first_stream(streams, &StreamKind::Video) {
+                    Some(s) => graph += &format!(
+                        "[{i}:{}]scale={width}:{height}:force_original_aspect_ratio=decrease,pad={width}:{height}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={fps},format=yuv420p[v{i}];",
+                        s.id
+                    ),
+                    None => graph += &format!("color=black:s={width}x{height}:r={fps}:d={duration},format=yuv420p[v{i}];"),
+                }
+                segments += &format!("[v{i}]");
+            }
+            if has_audio {
+                match first_stream(streams, &StreamKind::Audio) {
+                    Some(s) => graph += &format!(
+                        "[{i}:{}]aresample={CONCAT_SAMPLE_RATE},aformat=sample_fmts=fltp:channel_layouts=stereo[a{i}];",
+                        s.id
+                    ),
+                    None => graph += &format!("anullsrc=r={CONCAT_SAMPLE_RATE}:cl=stereo,atrim=duration={duration}[a{i}];"),
+                }
+                segments += &format!("[a{i}]");
+            }
+        }
+        match &params.transition {
+            Some(transition) => graph += &transition_graph(transition, &durations, has_video, has_audio),
+            None => {
+                graph += &format!("{segments}concat=n={}:v={}:a={}", inputs.len(), has_video as u8, has_audio as u8);
+                if has_video {
+                    graph += "[v]";
+                }
+                if has_audio {
+                    graph += "[a]";
+                }
+            }
+        }
+
+        for location in locations.iter() {
+            builder = builder.input(File::new(location));
+        }
+        let mut file = file.option(Parameter::key_value("filter_complex", graph));
+        if has_video {
+            file = file
+                .option(Parameter::key_value("map", "[v]"))
+                .option(Parameter::key_value("c:v", "libx264"));
+        }
+        if has_audio {
+            file = file
+                .option(Parameter::key_value("map", "[a]"))
+                .option(Parameter::key_value("c:a", "aac"));
+        }
+        file
+    };
+    builder.outputs = vec![file];
+
+    builder.run_and_upload(&video.id).await?;
+    Ok(())
+}
+
+/// First existing file for the font in the configured font directories, with its family name
+fn find_font(font: Font) -> Result<(PathBuf, &'static str), error::Worker> {
+    let dirs = config::get_font_paths();
+    font.get_files()
+        .iter()
+        .flat_map(|(name, family)| dirs.iter().map(move |dir| (dir.join(name), *family)))
+        .find(|(path, _)| path.is_file())
+        .ok_or(error::Encode::Overlay(error::Overlay::FontNotFound(font.get_name().to_owned())))
+        .context(error::EncodeSnafu)
+}
+
+/// Path escaped for a filter option
+fn filter_path(path: &Path) -> Result<String, error::Worker> {
+    let path = path.to_str().ok_or(error::Worker::Message { msg: "filter path str conversion error".to_owned() })?;
+    Ok(utils::escape_filter_value(path))
+}
+
+/// drawtext reading its text from a file, so that it doesn't need escaping
+async fn drawtext(dir: &Path, name: &str, text: &str, font: &Path, size: u32) -> Result<String, error::Worker> {
+    let text_file = dir.join(name);
+    tokio::fs::write(&text_file, text).await.context(error::IoSnafu)?;
+    Ok(format!(
+        "drawtext=expansion=none:fontfile={}:textfile={}:fontsize={size}",
+        filter_path(font)?,
+        filter_path(&text_file)?
+    ))
+}
+
+pub async fn overlay(video: &Video, params: &OverlayParameters) -> Result<(), error::Worker> {
+    let source = Input::resolve(&video.url).await?;
+    let url = source.location()?;
+    let image_source = match &params.overlay {
+        Overlay::Image(image) => Some(Input::resolve(&image.url).await?),
+        _ => None,
+    };
+
+    let streams = get_streams(video).await?;
+    let stream = streams
+        .iter()
+        .find(|s| s.kind == StreamKind::Video)
+        .ok_or(error::Encode::Overlay(error::Overlay::NoVideoStream))
+        .context(error::EncodeSnafu)?;
+    let (width, height) = stream
+        .get_display_size()
+        .ok_or(error::Encode::Overlay(error::Overlay::NoVideoStream))
+        .context(error::EncodeSnafu)?;
+    let dir = get_working_dir(&video.id).context(error::IoSnafu)?;
+
+    let mut builder = FfmpegBuilder::default(url);
+    let graph = match &params.overlay {
+        Overlay::Text(text) => {
+            if text.text.trim().is_empty() {
+                return Err(error::Encode::Overlay(error::Overlay::EmptyText)).context(error::EncodeSnafu)?;
+            }
+            for color in std::iter::once(&text.color).chain(text.box_color.iter()) {
+                if !is_valid_color(color) {
+                    return Err(error::Encode::Overlay(error::Overlay::InvalidColor(color.to_owned()))).context(error::EncodeSnafu)?;
+                }
+            }
+            let size = (height as u64 * text.size.clamp(1, 100) as u64 / 100).max(8) as u32;
+            let mut filter = drawtext(&dir, "overlay.txt", &text.text, &find_font(text.font)?.0, size).await?;
+            let (x, y) = text.position.get_coordinates("text_w", "text_h", "w", "h", size / 2);
+            filter += &format!(":fontcolor={}:x={x}:y={y}", text.color);
+            if let Some(box_color) = &text.box_color {
+                filter += &format!(":box=1:boxcolor={box_color}@0.6:boxborderw={}", (size / 4).max(1));
+            }
+            if text.outline {
+                filter += &format!(":borderw={}:bordercolor=black", (size / 16).max(1));
+            }
+            // Commas are escaped for the filtergraph
+            match (text.start, text.end) {
+                (Some(s), Some(e)) => filter += &format!(":enable=between(t\\,{}\\,{})", s.as_secs_f64(), e.as_secs_f64()),
+                (Some(s), None) => filter += &format!(":enable=gte(t\\,{})", s.as_secs_f64()),
+                (None, Some(e)) => filter += &format!(":enable=lte(t\\,{})", e.as_secs_f64()),
+                (None, None) => {}
+            }
+            format!("[0:{}]{filter},setsar=1[v]", stream.id)
+        }
+        Overlay::Image(image) => {
+            let image_url = image_source.as_ref().ok_or(error::Worker::Message { msg: "overlay image not resolved".to_owned() })?.location()?;
+            builder = builder.input(File::new(image_url));
+
+            let image_width = round_even(width as u64 * image.width.clamp(1, 100) as u64 / 100);
+            let (x, y) = image.position.get_coordinates("overlay_w", "overlay_h", "main_w", "main_h", width / 40);
+            format!(
+                "[1:v]format=rgba,scale={image_width}:-1,colorchannelmixer=aa={}[wm];\
+                [0:{}][wm]overlay={x}:{y}:format=auto,format=yuv420p,setsar=1[v]",
+                image.opacity.clamp(0.0, 1.0),
+                stream.id
+            )
+        }
+        Overlay::Meme { text } => {
+            let (font, _) = find_font(Font::Impact)?;
+            let size = (width / 10).max(16);
+            // Impact glyphs are about 0.6 times as wide as they are high
+            let lines = utils::wrap_text(text, (width * 10 / (size * 6)) as usize);
+            if lines.is_empty() {
+                return Err(error::Encode::Overlay(error::Overlay::EmptyText)).context(error::EncodeSnafu)?;
+            }
+            let line_height = size * 6 / 5;
+            let bar = round_even((lines.len() as u32 * line_height + size) as u64);
+            let (w, h) = (round_even(width as u64), round_even(height as u64));
+
+            let mut filters = vec![format!("pad={w}:{}:0:{bar}:white", h + bar)];
+            for (i, line) in lines.iter().enumerate() {
+                let filter = drawtext(&dir, &format!("caption_{i}.txt"), line, &font, size).await?;
+                filters.push(format!(
+                    "{filter}:fontcolor=black:x=(w-text_w)/2:y={}",
+                    size / 2 + i as u32 * line_height
+                ));
+            }
+            format!("[0:{}]{},setsar=1[v]", stream.id, filters.join(","))
+        }
+    };
+
     let file = File::new("pipe:1").option(Parameter::key_value("f", "mp4"))
     .option(Parameter::key_value("movflags", "frag_keyframe+empty_moov"))
-    .option(Parameter::key_value("c:v", "libx264"))
-    .option(Parameter::key_value("filter:v", format!("setpts={}*PTS", 1.0/params.speed_factor)))
-    .option(Parameter::key_value("filter:a", atempo))
-    .option(Parameter::key_value("c:a", "aac"));
+    .option(Parameter::key_value("filter_complex", graph))
+    .option(Parameter::key_value("map", "[v]"))
+    .option(Parameter::key_value("c:v", "libx264"));
+    builder.outputs = vec![map_first_audio(file, &streams)];
+
+    builder.run_and_upload(&video.id).await?;
+    Ok(())
+}
+
+fn find_subtitle_stream(streams: &[MediaStream], id: usize) -> Result<&MediaStream, error::Worker> {
+    streams
+        .iter()
+        .find(|s| s.id == id && s.kind == StreamKind::Subtitle)
+        .ok_or(error::Encode::Subtitle(error::Subtitle::StreamNotFound(id)))
+        .context(error::EncodeSnafu)
+}
+
+fn is_bitmap_subtitle(stream: &MediaStream) -> bool {
+    matches!(&stream.codec, Some(c) if BITMAP_SUBTITLE_CODECS.contains(&c.as_str()))
+}
+
+pub async fn subtitle(video: &Video, params: &SubtitleParameters) -> Result<(), error::Worker> {
+    let source = Input::resolve(&video.url).await?;
+    let url = source.location()?;
+    let file_source = match &params.operation {
+        SubtitleOperation::Extract { source: SubtitleSource::File(file), .. }
+        | SubtitleOperation::Burn { source: SubtitleSource::File(file), .. }
+        | SubtitleOperation::Mux { file, .. } => Some(Input::resolve(&file.url).await?),
+        _ => None,
+    };
+    let file_url = file_source.as_ref().map(|s| s.location()).transpose()?;
+    let file_url = || file_url.ok_or(error::Worker::Message { msg: "subtitle file not resolved".to_owned() });
+
+    let streams = get_streams(video).await?;
+
+    match &params.operation {
+        SubtitleOperation::Extract { source, format } => {
+            let (input, map) = match source {
+                SubtitleSource::Stream(id) => {
+                    if is_bitmap_subtitle(find_subtitle_stream(&streams, *id)?) {
+                        return Err(error::Encode::Subtitle(error::Subtitle::BitmapSubtitle)).context(error::EncodeSnafu)?;
+                    }
+                    (url, format!("0:{id}"))
+                }
+                SubtitleSource::File(_) => (file_url()?, "0:s:0".to_owned()),
+            };
+
+            // Text formats are small, written to a file rather than probed from a pipe
+            let dir = get_working_dir(&video.id).context(error::IoSnafu)?;
+            let output = dir.join(format!("subtitles.{}", format.get_file_extension()));
+            let output_str = output.to_str().ok_or(error::Worker::Message { msg: "subtitle path str conversion error".to_owned() })?;
+            let builder = FfmpegBuilder::new()
+                .option(Parameter::single("nostdin"))
+                .option(Parameter::single("y"))
+                .input(File::new(input))
+                .output(File::new(output_str)
+                    .option(Parameter::key_value("map", map))
+                    .option(Parameter::key_value("c:s", format.get_format()))
+                    .option(Parameter::key_value("f", format.get_format())));
+            run_to_completion(builder).await?;
+
+            upload_file(&output, &storage::output_key(&video.id), false).await
+        }
+        SubtitleOperation::Burn { source, style } => {
+            let video_stream = streams
+                .iter()
+                .find(|s| s.kind == StreamKind::Video)
+                .ok_or(error::Encode::Subtitle(error::Subtitle::NoVideoStream))
+                .context(error::EncodeSnafu)?;
+            let (font, family) = find_font(style.font)?;
+            let fonts_dir = filter_path(font.parent().unwrap_or(Path::new(".")))?;
+            let force_style = utils::escape_filter_value(&style.get_force_style(family));
+
+            let graph = match source {
+                SubtitleSource::Stream(id) => {
+                    let stream = find_subtitle_stream(&streams, *id)?;
+                    if is_bitmap_subtitle(stream) {
+                        // Image based subtitles are already styled, they are drawn as is
+                        format!("[0:{}][0:{id}]overlay=(W-w)/2:(H-h)/2,format=yuv420p[v]", video_stream.id)
+                    } else {
+                        // The filter counts subtitle streams only
+                        let index = streams.iter().filter(|s| s.kind == StreamKind::Subtitle).position(|s| s.id == *id).unwrap_or(0);
+                        format!(
+                            "[0:{}]subtitles=filename={}:si={index}:fontsdir={fonts_dir}:force_style={force_style}[v]",
+                            video_stream.id,
+                            utils::escape_filter_value(url)
+                        )
+                    }
+                }
+                SubtitleSource::File(_) => format!(
+                    "[0:{}]subtitles=filename={}:fontsdir={fonts_dir}:force_style={force_style}[v]",
+                    video_stream.id,
+                    utils::escape_filter_value(file_url()?)
+                ),
+            };
+
+            let file = File::new("pipe:1").option(Parameter::key_value("f", "mp4"))
+            .option(Parameter::key_value("movflags", "frag_keyframe+empty_moov"))
+            .option(Parameter::key_value("filter_complex", graph))
+            .option(Parameter::key_value("map", "[v]"))
+            .option(Parameter::key_value("c:v", "libx264"));
+
+            let mut builder = FfmpegBuilder::default(url);
+            builder.outputs = vec![map_first_audio(file, &streams)];
+            builder.run_and_upload(&video.id).await
+        }
+        SubtitleOperation::Mux { file: subtitle_file, container, language } => {
+            let mut file = File::new("pipe:1").option(Parameter::key_value("f", container.get_format()));
+            if container.is_mp4_family() {
+                file = file.option(Parameter::key_value("movflags", "frag_keyframe+empty_moov"));
+            }
+
+            // Same as remux, with the subtitle file as the last track
+            let mut output_index = 0;
+            let mut subtitle_index = 0;
+            for stream in streams.iter() {
+                let Some(codec) = &stream.codec else { continue };
+                let encoder = if container.accepts_codec(&stream.kind, codec) {
+                    "copy"
+                } else if is_bitmap_subtitle(stream) {
+                    continue;
+                } else {
+                    match container.get_default_encoder(&stream.kind) {
+                        Some(e) => e,
+                        None => continue,
+                    }
+                };
+                file = file
+                    .option(Parameter::key_value("map", format!("0:{}", stream.id)))
+                    .option(Parameter::key_value(format!("c:{output_index}"), encoder));
+                output_index += 1;
+                if stream.kind == StreamKind::Subtitle {
+                    subtitle_index += 1;
+                }
+            }
+            let subtitle_streams = get_streams(&Video { url: subtitle_file.url.to_owned(), filename: subtitle_file.filename.to_owned(), ..video.to_owned() }).await?;
+            let encoder = match subtitle_streams.iter().find(|s| s.kind == StreamKind::Subtitle).and_then(|s| s.codec.as_deref()) {
+                Some(codec) if container.accepts_codec(&StreamKind::Subtitle, codec) => "copy",
+                _ => container
+                    .get_default_encoder(&StreamKind::Subtitle)
+                    .ok_or(error::Encode::Subtitle(error::Subtitle::NoCompatibleStream))
+                    .context(error::EncodeSnafu)?,
+            };
+            file = file
+                .option(Parameter::key_value("map", "1:s:0"))
+                .option(Parameter::key_value(format!("c:{output_index}"), encoder));
+            if let Some(language) = language {
+                file = file.option(Parameter::key_value(format!("metadata:s:s:{subtitle_index}"), format!("language={language}")));
+            }
+
+            let mut builder = FfmpegBuilder::default(url).input(File::new(file_url()?));
+            builder.outputs = vec![file];
+            builder.run_and_upload(&video.id).await
+        }
+    }
+}
+
+/// Frames sampled over the video to pick a thumbnail from
+const THUMBNAIL_SAMPLES: u32 = 100;
+/// Width of a frame in a contact sheet
+const CONTACT_SHEET_TILE_WIDTH: u32 = 320;
+
+pub async fn image(video: &Video, params: &ImageParameters) -> Result<(), error::Worker> {
+    let source = Input::resolve(&video.url).await?;
+    let url = source.location()?;
+
+    let streams = get_streams(video).await?;
+    let stream = streams
+        .iter()
+        .find(|s| s.kind == StreamKind::Video)
+        .ok_or(error::Encode::Image(error::Image::NoVideoStream))
+        .context(error::EncodeSnafu)?;
+    // Container duration in seconds, 0 when unknown
+    let duration = stream.duration.max(0) as f64 / 1_000_000.0;
+
+    let mut input = File::new(url);
+    let filter = match &params.kind {
+        ImageKind::Frame(timestamp) => {
+            let timestamp = timestamp.as_secs_f64();
+            if duration > 0.0 && timestamp >= duration {
+                return Err(error::Encode::Image(error::Image::OutOfRange)).context(error::EncodeSnafu)?;
+            }
+            input = input.option(Parameter::key_value("ss", timestamp.to_string()));
+            "null".to_owned()
+        }
+        // The filter only compares the frames of a batch, it is spread over the whole video
+        ImageKind::Thumbnail if duration > 0.0 => format!("fps={},thumbnail={THUMBNAIL_SAMPLES}", THUMBNAIL_SAMPLES as f64 / duration),
+        ImageKind::Thumbnail => format!("thumbnail={THUMBNAIL_SAMPLES}"),
+        ImageKind::ContactSheet { columns, rows } => {
+            let (columns, rows) = (*columns.clamp(&1, &10), *rows.clamp(&1, &10));
+            let interval = match duration > 0.0 {
+                true => duration / (columns * rows) as f64,
+                false => 1.0,
+            };
+            // Frames are taken in the middle of each interval, the seek resets timestamps
+            let offset = interval / 2.0;
+            input = input.option(Parameter::key_value("ss", offset.to_string()));
+
+            let (font, _) = find_font(Font::Sans)?;
+            let size = CONTACT_SHEET_TILE_WIDTH / 12;
+            format!(
+                "fps=1/{interval},scale={CONTACT_SHEET_TILE_WIDTH}:-2,\
+                drawtext=fontfile={}:text={}:fontsize={size}:fontcolor=white:box=1:boxcolor=black@0.6:boxborderw=4:x=w-text_w-8:y=h-text_h-8,\
+                tile={columns}x{rows}:padding=4:margin=4",
+                filter_path(&font)?,
+                utils::escape_filter_value(&format!("%{{pts:hms:{offset}}}"))
+            )
+        }
+    };
+
+    let dir = get_working_dir(&video.id).context(error::IoSnafu)?;
+    let output = dir.join(format!("image.{}", params.format.get_file_extension()));
+    let output_str = output.to_str().ok_or(error::Worker::Message { msg: "image path str conversion error".to_owned() })?;
+    let mut file = File::new(output_str)
+        .option(Parameter::key_value("map", format!("0:{}", stream.id)))
+        .option(Parameter::key_value("filter:v", filter))
+        .option(Parameter::key_value("frames:v", "1"))
+        .option(Parameter::key_value("c:v", params.format.get_encoder()))
+        .option(Parameter::key_value("update", "1"))
+        .option(Parameter::key_value("f", "image2"));
+    // libwebp would read it as its quality
+    if params.format == ImageFormat::Jpeg {
+        file = file.option(Parameter::key_value("q:v", "2"));
+    }
+    let builder = FfmpegBuilder::new()
+        .option(Parameter::single("nostdin"))
+        .option(Parameter::single("y"))
+        .input(input)
+        .output(file);
+    run_to_completion(builder).await?;
+
+    upload_file(&output, &storage::output_key(&video.id), true).await
+}
+
+/// Memory the reverse filters may use, they buffer the whole clip decoded
+const REVERSE_MEMORY_BUDGET: u64 = 1 << 30;
+/// Longest input that is reversed, in seconds
+const REVERSE_MAX_DURATION: f64 = 10.0 * 60.0;
+
+/// Path of a file in the job directory
+fn job_file(dir: &Path, name: &str) -> Result<String, error::Worker> {
+    dir.join(name)
+        .to_str()
+        .map(|p| p.to_owned())
+        .ok_or(error::Worker::Message { msg: "job file path str conversion error".to_owned() })
+}
+
+/// Encoding shared by the parts of a reversed clip, so that they can be concatenated without re-encoding
+fn reverse_encoding(file: File) -> File {
+    file.option(Parameter::key_value("c:v", "libx264"))
+        .option(Parameter::key_value("pix_fmt", "yuv420p"))
+        .option(Parameter::key_value("c:a", "aac"))
+        .option(Parameter::key_value("ar", "48000"))
+        .option(Parameter::key_value("ac", "2"))
+        .option(Parameter::key_value("f", "mp4"))
+}
+
+pub async fn reverse(video: &Video, params: &ReverseParameters) -> Result<(), error::Worker> {
+    let source = Input::resolve(&video.url).await?;
+    let url = source.location()?;
+
+    let streams = get_streams(video).await?;
+    let video_stream = streams.iter().find(|s| s.kind == StreamKind::Video);
+    let audio_stream = streams.iter().find(|s| s.kind == StreamKind::Audio);
+    let reverse_video = video_stream.is_some() && matches!(params.mode, ReverseMode::Video | ReverseMode::Both);
+    let reverse_audio = audio_stream.is_some() && matches!(params.mode, ReverseMode::Audio | ReverseMode::Both);
+    if !reverse_video && !reverse_audio {
+        return Err(error::Encode::Reverse(error::Reverse::NoStream)).context(error::EncodeSnafu)?;
+    }
+    let duration = container_duration(&streams);
+    if duration > REVERSE_MAX_DURATION {
+        return Err(error::Encode::Reverse(error::Reverse::TooLong)).context(error::EncodeSnafu)?;
+    }
+
+    // Size of one second of decoded yuv420p frames and float samples
+    let mut rate = 0;
+    if let (Some(s), true) = (video_stream, reverse_video) {
+        let fps = s.frame_rate.filter(|f| *f > 0.0).unwrap_or(30.0).ceil() as u64;
+        rate += s.width.unwrap_or(1920) as u64 * s.height.unwrap_or(1080) as u64 * 3 / 2 * fps;
+    }
+    if let (Some(s), true) = (audio_stream, reverse_audio) {
+        rate += s.sample_rate.unwrap_or(48000) as u64 * s.channels.unwrap_or(2) as u64 * 4;
+    }
+    let segment_length = ((REVERSE_MEMORY_BUDGET / rate.max(1)) as f64).max(1.0);
+
+    let dir = get_working_dir(&video.id).context(error::IoSnafu)?;
+    let back = job_file(&dir, "back.mp4")?;
+
+    if duration <= segment_length {
+        let mut graph = Vec::new();
+        let mut file = File::new(&back);
+        if let Some(s) = video_stream {
+            file = if reverse_video {
+                graph.push(format!("[0:{}]reverse[v]", s.id));
+                file.option(Parameter::key_value("map", "[v]"))
+            } else {
+                file.option(Parameter::key_value("map", format!("0:{}", s.id)))
+            };
+        }
+        if let Some(s) = audio_stream {
+            file = if reverse_audio {
+                graph.push(format!("[0:{}]areverse[a]", s.id));
+                file.option(Parameter::key_value("map", "[a]"))
+            } else {
+                file.option(Parameter::key_value("map", format!("0:{}", s.id)))
+            };
+        }
+        file = file.option(Parameter::key_value("filter_complex", graph.join(";")));
+
+        let builder = FfmpegBuilder::new()
+            .option(Parameter::single("nostdin"))
+            .option(Parameter::single("y"))
+            .input(File::new(url))
+            .output(reverse_encoding(file));
+        run_to_completion(builder).await?;
+    } else {
+        // Too long to be buffered, segments are reversed one by one and joined last to first
+        let count = (duration / segment_length).ceil() as usize;
+        let mut entries = String::new();
+        for i in 0..count {
+            let segment = job_file(&dir, &format!("reverse_{i}.mp4"))?;
+            let mut file = File::new(&segment);
+            if let (Some(s), true) = (video_stream, reverse_video) {
+                file = file
+                    .option(Parameter::key_value("map", format!("0:{}", s.id)))
+                    .option(Parameter::key_value("filter:v", "reverse"));
+            }
+            if let (Some(s), true) = (audio_stream, reverse_audio) {
+                file = file
+                    .option(Parameter::key_value("map", format!("0:{}", s.id)))
+                    .option(Parameter::key_value("filter:a", "areverse"));
+            }
+            let builder = FfmpegBuilder::new()
+                .option(Parameter::single("nostdin"))
+                .option(Parameter::single("y"))
+                .input(File::new(url)
+                    .option(Parameter::key_value("ss", (i as f64 * segment_length).to_string()))
+                    .option(Parameter::key_value("t", segment_length.to_string())))
+                .output(reverse_encoding(file));
+            run_to_completion(builder).await?;
+            entries.insert_str(0, &concat_list_entry(&segment));
+        }
+        let list = job_file(&dir, "reverse.txt")?;
+        tokio::fs::write(&list, entries).await.context(error::IoSnafu)?;
+
+        // Streams that keep playing forward come from the original
+        let mut file = File::new(&back).option(Parameter::key_value("f", "mp4"));
+        if let Some(s) = video_stream {
+            file = match reverse_video {
+                true => file.option(Parameter::key_value("map", "0:v:0")).option(Parameter::key_value("c:v", "copy")),
+                false => file
+                    .option(Parameter::key_value("map", format!("1:{}", s.id)))
+                    .option(Parameter::key_value("c:v", "libx264"))
+                    .option(Parameter::key_value("pix_fmt", "yuv420p")),
+            };
+        }
+        if let Some(s) = audio_stream {
+            file = match reverse_audio {
+                true => file.option(Parameter::key_value("map", "0:a:0")).option(Parameter::key_value("c:a", "copy")),
+                false => file
+                    .option(Parameter::key_value("map", format!("1:{}", s.id)))
+                    .option(Parameter::key_value("c:a", "aac"))
+                    .option(Parameter::key_value("ar", "48000"))
+                    .option(Parameter::key_value("ac", "2")),
+            };
+        }
+        let builder = FfmpegBuilder::new()
+            .option(Parameter::single("nostdin"))
+            .option(Parameter::single("y"))
+            .input(File::new(&list).option(Parameter::key_value("f", "concat")).option(Parameter::key_value("safe", "0")))
+            .input(File::new(url))
+            .output(file);
+        run_to_completion(builder).await?;
+    }
+
+    // The untouched clip followed by the reversed one
+    let result = if params.boomerang {
+        let forward = job_file(&dir, "forward.mp4")?;
+        let mut file = File::new(&forward);
+        for s in video_stream.iter().chain(audio_stream.iter()) {
+            file = file.option(Parameter::key_value("map", format!("0:{}", s.id)));
+        }
+        let builder = FfmpegBuilder::new()
+            .option(Parameter::single("nostdin"))
+            .option(Parameter::single("y"))
+            .input(File::new(url))
+            .output(reverse_encoding(file));
+        run_to_completion(builder).await?;
+
+        let list = job_file(&dir, "boomerang.txt")?;
+        tokio::fs::write(&list, [concat_list_entry(&forward), concat_list_entry(&back)].concat()).await.context(error::IoSnafu)?;
+        let boomerang = job_file(&dir, "boomerang.mp4")?;
+        let builder = FfmpegBuilder::new()
+            .option(Parameter::single("nostdin"))
+            .option(Parameter::single("y"))
+            .input(File::new(&list).option(Parameter::key_value("f", "concat")).option(Parameter::key_value("safe", "0")))
+            .output(File::new(&boomerang).option(Parameter::key_value("c", "copy")));
+        run_to_completion(builder).await?;
+        boomerang
+    } else {
+        back
+    };
+
+    let mut builder = FfmpegBuilder::default(&result);
+    builder.inputs = vec![File::new(&result)
+        .option(Parameter::key_value("stream_loop", (params.loops.clamp(1, 10) - 1).to_string()))];
+    builder.outputs = vec![File::new("pipe:1").option(Parameter::key_value("f", "mp4"))
+        .option(Parameter::key_value("movflags", "frag_keyframe+empty_moov"))
+        .option(Parameter::key_value("map", "0"))
+        .option(Parameter::key_value("c", "copy"))];
+
+    builder.run_and_upload(&video.id).await?;
+    Ok(())
+}
+
+pub async fn fade(video: &Video, params: &FadeParameters) -> Result<(), error::Worker> {
+    let source = Input::resolve(&video.url).await?;
+    let url = source.location()?;
+
+    let streams = get_streams(video).await?;
+    let duration = container_duration(&streams);
+
+    // Timestamps start at 0 so that fades can be placed from the container duration
+    let mut video_filters = vec!["setpts=PTS-STARTPTS".to_owned()];
+    let mut audio_filters = vec!["asetpts=PTS-STARTPTS".to_owned()];
+    let (fade_in, fade_out) = fade_lengths(params, duration);
+    if let Some(length) = fade_in {
+        video_filters.push(format!("fade=t=in:st=0:d={length}"));
+        audio_filters.push(format!("afade=t=in:st=0:d={length}"));
+    }
+    if let Some(length) = fade_out {
+        let start = duration - length;
+        video_filters.push(format!("fade=t=out:st={start}:d={length}"));
+        audio_filters.push(format!("afade=t=out:st={start}:d={length}"));
+    }
+
+    let container = VideoContainer::get_reencode_container(video.get_container());
+    let mut file = File::new("pipe:1").option(Parameter::key_value("f", container.get_format()));
+    if container.is_mp4_family() {
+        file = file.option(Parameter::key_value("movflags", "frag_keyframe+empty_moov"));
+    }
+
+    let mut mapped = false;
+    let video_stream = streams.iter().find(|s| s.kind == StreamKind::Video);
+    if let (Some(s), Some(encoder)) = (video_stream, container.get_default_encoder(&StreamKind::Video)) {
+        file = file
+            .option(Parameter::key_value("map", format!("0:{}", s.id)))
+            .option(Parameter::key_value("filter:v", video_filters.join(",")))
+            .option(Parameter::key_value("c:v", encoder));
+        mapped = true;
+    }
+    let audio_stream = streams.iter().find(|s| s.kind == StreamKind::Audio);
+    if let (Some(s), Some(encoder)) = (audio_stream, container.get_default_encoder(&StreamKind::Audio)) {
+        file = file
+            .option(Parameter::key_value("map", format!("0:{}", s.id)))
+            .option(Parameter::key_value("filter:a", audio_filters.join(",")))
+            .option(Parameter::key_value("c:a", encoder));
+        if !container.is_lossless() {
+            file = file.option(Parameter::key_value("b:a", "192k"));
+        }
+        mapped = true;
+    }
+    if !mapped {
+        return Err(error::Encode::Fade(error::Fade::NoStream)).context(error::EncodeSnafu)?;
+    }
+
+    let mut builder = FfmpegBuilder::default(url);
     builder.outputs = vec![file];
 
     builder.run_and_upload(&video.id).await?;
     Ok(())
 }
 
+/// Lengths of the fades in seconds, shortened together so they fit in `duration` without overlapping
+fn fade_lengths(params: &FadeParameters, duration: f64) -> (Option<f64>, Option<f64>) {
+    let fade_in = params.fade_in.map(|d| d.as_secs_f64());
+    let fade_out = params.fade_out.map(|d| d.as_secs_f64());
+    let total = fade_in.unwrap_or(0.0) + fade_out.unwrap_or(0.0);
+    let scale = if total > duration { duration.max(0.0) / total } else { 1.0 };
+    (fade_in.map(|l| l * scale), fade_out.map(|l| l * scale))
+}
+
 // Code without using lib
 
 // pub fn encode_to_size(path: &str, t_size: f32, dest_path: &str) -> Result<(), EncodeToSizeError> {
@@ -389,4 +2222,197 @@ mod tests {
         // .unwrap();
         assert_ne!(0, 0);
     }
+
+    #[test]
+    fn parse_cropdetect() {
+        let stderr = "[Parsed_cropdetect_0 @ 0x5581] x1:0 x2:1919 y1:140 y2:939 w:1920 h:800 x:0 y:140 pts:1 t:0.04 crop=1920:800:0:140\n\
+            [Parsed_cropdetect_0 @ 0x5581] x1:0 x2:1919 y1:138 y2:941 w:1920 h:804 x:0 y:138 pts:2 t:0.08 crop=1920:804:0:138\n\
+            frame=   50 fps=0.0 q=-0.0 Lsize=N/A time=00:00:02.00";
+        assert_eq!(utils::parse_cropdetect(stderr), Some((0, 138, 1920, 804)));
+        assert_eq!(utils::parse_cropdetect("no crop here"), None);
+    }
+
+    #[test]
+    fn parse_loudnorm() {
+        let stderr = r#"[Parsed_loudnorm_0 @ 0x55d3]
+{
+	"input_i" : "-27.61",
+	"input_tp" : "-4.47",
+	"input_lra" : "18.06",
+	"input_thresh" : "-39.20",
+	"output_i" : "-16.58",
+	"output_tp" : "-1.50",
+	"output_lra" : "14.78",
+	"output_thresh" : "-27.71",
+	"normalization_type" : "dynamic",
+	"target_offset" : "0.58"
+}
+size=N/A time=00:00:10.00 bitrate=N/A speed= 120x"#;
+        assert_eq!(utils::parse_loudnorm(stderr), Some(utils::LoudnessMeasure {
+            integrated: -27.61,
+            true_peak: -4.47,
+            range: 18.06,
+            threshold: -39.2,
+            offset: 0.58,
+        }));
+        assert_eq!(utils::parse_loudnorm("no measure"), None);
+    }
+
+    #[test]
+    fn display_rotation() {
+        let matrix = |values: [i32; 9]| values.iter().flat_map(|v| (v << 16).to_ne_bytes()).collect::<Vec<u8>>();
+        assert_eq!(utils::get_display_rotation(&matrix([1, 0, 0, 0, 1, 0, 0, 0, 1 << 14])), Some(0));
+        assert_eq!(utils::get_display_rotation(&matrix([0, 1, 0, -1, 0, 0, 0, 0, 1 << 14])), Some(90));
+        assert_eq!(utils::get_display_rotation(&matrix([0, -1, 0, 1, 0, 0, 0, 0, 1 << 14])), Some(270));
+        assert_eq!(utils::get_display_rotation(&[0; 8]), None);
+    }
+
+    #[test]
+    fn cut_keyframes() {
+        let stderr = "[Parsed_showinfo_1 @ 0x55d3] n:   0 pts:      0 pts_time:0       pos:       48 fmt:yuv420p sar:1/1 s:1280x720 i:P iskey:1 type:I checksum:0\n\
+            [Parsed_showinfo_1 @ 0x55d3] n:   1 pts:2002000 pts_time:2.002   pos:   190210 fmt:yuv420p sar:1/1 s:1280x720 i:P iskey:1 type:I checksum:0\n\
+            [Parsed_showinfo_1 @ 0x55d3] n:   2 pts:4004000 pts_time:4.004   pos:   380120 fmt:yuv420p sar:1/1 s:1280x720 i:P iskey:1 type:I checksum:0";
+        let keyframes = utils::parse_keyframes(stderr);
+        assert_eq!(keyframes, vec![0.0, 2.002, 4.004]);
+        assert_eq!(utils::snap_to_keyframes(&[(1.0, 3.0), (4.5, 6.0)], &keyframes), vec![(0.0, 3.0), (4.004, 6.0)]);
+        assert_eq!(
+            utils::smart_cut_parts(1.0, 5.0, &keyframes),
+            vec![(1.0, 2.002, false), (2.002, 4.004, true), (4.004, 5.0, false)]
+        );
+        // No keyframe in between, everything is re-encoded
+        assert_eq!(utils::smart_cut_parts(2.5, 3.5, &keyframes), vec![(2.5, 3.5, false)]);
+    }
+
+    #[test]
+    fn dead_air_ranges() {
+        let stderr = "[silencedetect @ 0x55d3] silence_start: 1.5\n\
+            [silencedetect @ 0x55d3] silence_end: 3.25 | silence_duration: 1.75\n\
+            [blackdetect @ 0x55d4] black_start:0 black_end:2.002 black_duration:2.002\n\
+            [silencedetect @ 0x55d3] silence_start: 9.8";
+        assert_eq!(utils::parse_detected_ranges(stderr, "silence_start:", "silence_end:"), vec![(1.5, Some(3.25)), (9.8, None)]);
+        assert_eq!(utils::parse_detected_ranges(stderr, "black_start:", "black_end:"), vec![(0.0, Some(2.002))]);
+        assert!(utils::parse_detected_ranges(stderr, "freeze_start:", "freeze_end:").is_empty());
+    }
+
+    #[test]
+    fn scene_times() {
+        let stderr = "[Parsed_showinfo_3 @ 0x55d3] config in time_base: 1/1000000, frame_rate: 30/1\n\
+            [Parsed_showinfo_3 @ 0x55d3] n:   0 pts:1500000 pts_time:1.5     pos:  1234 fmt:yuv420p sar:1/1 s:320x180 i:P iskey:0 type:P\n\
+            [Parsed_showinfo_3 @ 0x55d3] n:   1 pts:1800000 pts_time:1.8     pos:  2345 fmt:yuv420p sar:1/1 s:320x180 i:P iskey:0 type:P\n\
+            [Parsed_showinfo_3 @ 0x55d3] n:   2 pts:7000000 pts_time:7       pos:  3456 fmt:yuv420p sar:1/1 s:320x180 i:P iskey:1 type:I";
+        let times = utils::parse_frame_times(stderr);
+        assert_eq!(times, vec![1.5, 1.8, 7.0]);
+        assert_eq!(utils::drop_close(&times, 1.0), vec![1.5, 7.0]);
+        assert_eq!(utils::drop_close(&[0.4, 2.0], 1.0), vec![2.0]);
+    }
+
+    /// Fresh directory under the system temp dir
+    fn input_temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ffedit-input-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn allowed_paths() {
+        let root = input_temp_dir("allowed");
+        let allowed = root.join("allowed");
+        std::fs::create_dir_all(&allowed).unwrap();
+        std::fs::write(allowed.join("video.mp4"), b"\x00\x00\x00\x18ftypmp42").unwrap();
+        std::fs::write(root.join("secret.txt"), b"secret").unwrap();
+        let allowed_paths = [allowed.to_owned()];
+
+        assert!(matches!(input::resolve_path(&allowed.join("video.mp4"), &allowed_paths).await, Ok(input::Input::Path(_))));
+        // Outside of the allowed directories, directly or through `..`
+        assert!(input::resolve_path(&root.join("secret.txt"), &allowed_paths).await.is_err());
+        assert!(input::resolve_path(&allowed.join("../secret.txt"), &allowed_paths).await.is_err());
+        // Directories and missing files
+        assert!(input::resolve_path(&allowed, &allowed_paths).await.is_err());
+        assert!(input::resolve_path(&allowed.join("missing.mp4"), &allowed_paths).await.is_err());
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(root.join("secret.txt"), allowed.join("link.mp4")).unwrap();
+            assert!(input::resolve_path(&allowed.join("link.mp4"), &allowed_paths).await.is_err());
+        }
+        // Playlists inside an allowed directory
+        std::fs::write(allowed.join("list.m3u8"), b"#EXTM3U\n#EXTINF:1,\n../secret.txt\n").unwrap();
+        assert!(input::resolve_path(&allowed.join("list.m3u8"), &allowed_paths).await.is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn playlists() {
+        assert!(input::is_playlist(b"#EXTM3U\n#EXT-X-VERSION:3\n"));
+        assert!(input::is_playlist("\u{feff}  #extm3u\n".as_bytes()));
+        assert!(input::is_playlist(b"ffconcat version 1.0\nfile a.mp4\n"));
+        assert!(input::is_playlist(b"<?xml version=\"1.0\"?>\n<MPD xmlns=\"urn:mpeg:dash:schema:mpd:2011\">"));
+        assert!(!input::is_playlist(b"\x00\x00\x00\x18ftypmp42"));
+        assert!(!input::is_playlist(b""));
+    }
+
+    #[test]
+    fn fades() {
+        let params = |fade_in: Option<u64>, fade_out: Option<u64>| FadeParameters {
+            fade_in: fade_in.map(std::time::Duration::from_secs),
+            fade_out: fade_out.map(std::time::Duration::from_secs),
+        };
+        assert_eq!(fade_lengths(&params(Some(1), Some(2)), 10.0), (Some(1.0), Some(2.0)));
+        // Each fade stays in the clip
+        assert_eq!(fade_lengths(&params(Some(5), None), 2.0), (Some(2.0), None));
+        assert_eq!(fade_lengths(&params(None, Some(5)), 2.0), (None, Some(2.0)));
+        // Both are shortened so they don't overlap
+        assert_eq!(fade_lengths(&params(Some(3), Some(1)), 2.0), (Some(1.5), Some(0.5)));
+    }
+
+    #[test]
+    fn speed_graphs() {
+        let params = |speed_factor, mode| SpeedParameters { speed_factor, mode };
+        assert_eq!(
+            speed_filters(&params(5.0, SpeedMode::KeepPitch), 48000, 30.0),
+            (Some("setpts=PTS/5".to_owned()), "atempo=2,atempo=2,atempo=1.25".to_owned())
+        );
+        assert_eq!(
+            speed_filters(&params(2.0, SpeedMode::Chipmunk), 44100, 30.0).1,
+            "asetrate=88200,aresample=44100"
+        );
+        assert_eq!(
+            speed_filters(&params(0.5, SpeedMode::PitchShift), 48000, 30.0),
+            (None, "asetrate=24000,aresample=48000,atempo=2".to_owned())
+        );
+        assert_eq!(
+            speed_filters(&params(0.25, SpeedMode::SlowMotion { interpolate: true }), 48000, 25.0),
+            (Some("setpts=PTS/0.25,minterpolate=fps=25:mi_mode=mci".to_owned()), "atempo=0.5,atempo=0.5".to_owned())
+        );
+    }
+
+    #[test]
+    fn split_times() {
+        assert_eq!(utils::split_every(4.0, 10.0), vec![4.0, 8.0]);
+        // No empty last part
+        assert_eq!(utils::split_every(5.0, 10.0), vec![5.0]);
+        assert!(utils::split_every(0.0, 10.0).is_empty());
+    }
+
+    #[test]
+    fn transitions() {
+        let transition = Transition { kind: TransitionKind::Fade, duration: std::time::Duration::from_secs(1) };
+        assert_eq!(
+            transition_graph(&transition, &[5.0, 4.0, 6.0], true, true),
+            "[v0][v1]xfade=transition=fade:duration=1:offset=4[vx1];[a0][a1]acrossfade=d=1[ax1];\
+            [vx1][v2]xfade=transition=fade:duration=1:offset=7[v];[ax1][a2]acrossfade=d=1[a]"
+        );
+        // Shortened to half of the shortest clip
+        assert_eq!(transition_graph(&transition, &[1.0, 3.0], false, true), "[a0][a1]acrossfade=d=0.5[a]");
+    }
+
+    #[test]
+    fn filter_text() {
+        assert_eq!(utils::escape_filter_value("/tmp/job/font.ttf"), "/tmp/job/font.ttf");
+        assert_eq!(utils::escape_filter_value("C:\\a,b"), "C\\\\:\\\\\\\\a\\,b");
+        assert_eq!(utils::wrap_text("one does not simply  walk", 9), vec!["one does", "not", "simply", "walk"]);
+        assert_eq!(utils::wrap_text("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+        assert!(utils::wrap_text("  ", 4).is_empty());
+    }
 }
@@ -1,4 +1,9 @@
+use std::{pin::Pin, task::{Context, Poll}};
+
 use ffmpeg::format::context::Input;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, ReadBuf};
 
 
 #[inline(always)]
@@ -9,7 +14,8 @@ pub fn get_duration(input: &Input) -> f32 {
 #[inline(always)]
 pub fn get_audio_bitrate(input: &Input) -> f32 {
     for stream in input.streams() {
-        let codec = ffmpeg::codec::context::Context::from_parameters(stream.parameters()).unwrap();
+        // Streams libavcodec can't describe have no known bitrate
+        let Ok(codec) = ffmpeg::codec::context::Context::from_parameters(stream.parameters()) else { continue };
         if codec.medium() == ffmpeg::media::Type::Audio {
             if let Ok(audio) = codec.decoder().audio() {
                 return audio.bit_rate() as f32 / 1024.0;
@@ -17,4 +23,242 @@ pub fn get_audio_bitrate(input: &Input) -> f32 {
         }
     }
     0.0
-}
\ No newline at end of file
+}
+/// Clockwise rotation of a display matrix side data, rounded to a multiple of 90 degrees
+pub fn get_display_rotation(matrix: &[u8]) -> Option<u32> {
+    // 3x3 matrix of native endian 16.16 fixed point values
+    let value = |i: usize| -> Option<f64> {
+        let bytes = matrix.get(i * 4..i * 4 + 4)?.try_into().ok()?;
+        Some(i32::from_ne_bytes(bytes) as f64 / 65536.0)
+    };
+    let (a, b, c, d) = (value(0)?, value(1)?, value(3)?, value(4)?);
+    let scale0 = a.hypot(c);
+    let scale1 = b.hypot(d);
+    if scale0 == 0.0 || scale1 == 0.0 {
+        return None;
+    }
+    let degrees = (b / scale1).atan2(a / scale0).to_degrees();
+    let rounded = ((degrees / 90.0).round() as i64 * 90).rem_euclid(360);
+    Some(rounded as u32)
+}
+
+/// Measurements printed by `loudnorm` with `print_format=json`, as strings
+#[derive(Deserialize)]
+struct LoudnormOutput {
+    input_i: String,
+    input_tp: String,
+    input_lra: String,
+    input_thresh: String,
+    target_offset: String,
+}
+
+/// Loudness of an input measured by a first `loudnorm` pass
+#[derive(Debug, PartialEq)]
+pub struct LoudnessMeasure {
+    pub integrated: f64,
+    pub true_peak: f64,
+    pub range: f64,
+    pub threshold: f64,
+    pub offset: f64,
+}
+
+/// Last json object of the stderr of a `loudnorm` analysis pass
+pub fn parse_loudnorm(stderr: &str) -> Option<LoudnessMeasure> {
+    let start = stderr.rfind('{')?;
+    let end = stderr[start..].find('}')? + start;
+    let output: LoudnormOutput = serde_json::from_str(&stderr[start..=end]).ok()?;
+    Some(LoudnessMeasure {
+        integrated: output.input_i.parse().ok()?,
+        true_peak: output.input_tp.parse().ok()?,
+        range: output.input_lra.parse().ok()?,
+        threshold: output.input_thresh.parse().ok()?,
+        offset: output.target_offset.parse().ok()?,
+    })
+}
+
+/// Last `crop=w:h:x:y` reported by the cropdetect filter, as `(x, y, width, height)`
+pub fn parse_cropdetect(stderr: &str) -> Option<(u32, u32, u32, u32)> {
+    let crop = stderr.lines().rev().find_map(|l| l.split("crop=").nth(1))?;
+    let values: Vec<u32> = crop.trim().split(':').map(|v| v.parse().ok()).collect::<Option<_>>()?;
+    match values[..] {
+        [w, h, x, y] => Some((x, y, w, h)),
+        _ => None,
+    }
+}
+
+/// Timestamp of a frame logged by `settb=AVTB,showinfo`, in seconds
+fn parse_frame_time(line: &str) -> Option<f64> {
+    let pts: i64 = line.split(" pts:").nth(1)?.split_whitespace().next()?.parse().ok()?;
+    Some(pts as f64 / 1_000_000.0)
+}
+
+/// Timestamps of the keyframes logged by `settb=AVTB,showinfo`, in seconds
+pub fn parse_keyframes(stderr: &str) -> Vec<f64> {
+    stderr.lines().filter(|l| l.contains("iskey:1")).filter_map(parse_frame_time).collect()
+}
+
+/// Timestamps of every frame logged by `settb=AVTB,showinfo`, in seconds
+pub fn parse_frame_times(stderr: &str) -> Vec<f64> {
+    stderr.lines().filter(|l| l.contains("showinfo")).filter_map(parse_frame_time).collect()
+}
+
+/// Drops the timestamps closer than `min_gap` seconds to the previous one kept, or to 0
+pub fn drop_close(times: &[f64], min_gap: f64) -> Vec<f64> {
+    let mut kept: Vec<f64> = Vec::with_capacity(times.len());
+    for time in times {
+        if time - kept.last().copied().unwrap_or(0.0) >= min_gap {
+            kept.push(*time);
+        }
+    }
+    kept
+}
+
+/// Moves the start of each range back to the keyframe a stream copy would start from
+pub fn snap_to_keyframes(ranges: &[(f64, f64)], keyframes: &[f64]) -> Vec<(f64, f64)> {
+    ranges
+        .iter()
+        .map(|(start, end)| {
+            let keyframe = keyframes.iter().copied().rev().find(|k| *k <= *start).unwrap_or(0.0);
+            (keyframe, *end)
+        })
+        .collect()
+}
+
+/// Splits a range into parts as `(start, end, copy)`: re-encoded up to the first keyframe,
+/// copied until the last one and re-encoded after it
+pub fn smart_cut_parts(start: f64, end: f64, keyframes: &[f64]) -> Vec<(f64, f64, bool)> {
+    const EPSILON: f64 = 0.001;
+    let first = keyframes.iter().copied().find(|k| *k >= start - EPSILON);
+    let last = keyframes.iter().copied().rev().find(|k| *k <= end + EPSILON);
+    match (first, last) {
+        (Some(first), Some(last)) if first < last => {
+            let mut parts = Vec::with_capacity(3);
+            if first - start > EPSILON {
+                parts.push((start, first, false));
+            }
+            parts.push((first, last, true));
+            if end - last > EPSILON {
+                parts.push((last, end, false));
+            }
+            parts
+        }
+        _ => vec![(start, end, false)],
+    }
+}
+
+/// Ranges logged by a detection filter as `{start_key} <t>` and `{end_key} <t>`,
+/// a range still open at the end of the input has no end
+pub fn parse_detected_ranges(stderr: &str, start_key: &str, end_key: &str) -> Vec<(f64, Option<f64>)> {
+    let value = |line: &str, key: &str| -> Option<f64> { line.split(key).nth(1)?.split_whitespace().next()?.parse().ok() };
+    let mut ranges: Vec<(f64, Option<f64>)> = Vec::new();
+    for line in stderr.lines() {
+        if let Some(start) = value(line, start_key) {
+            ranges.push((start, None));
+        }
+        if let Some(end) = value(line, end_key) {
+            if let Some(last) = ranges.last_mut().filter(|r| r.1.is_none()) {
+                last.1 = Some(end);
+            }
+        }
+    }
+    ranges
+}
+
+/// Timestamps splitting `duration` seconds into parts of `length` seconds
+pub fn split_every(length: f64, duration: f64) -> Vec<f64> {
+    if length <= 0.0 || !length.is_finite() {
+        return Vec::new();
+    }
+    (1..)
+        .map(|i| i as f64 * length)
+        .take_while(|t| duration - t > 0.001)
+        .collect()
+}
+
+/// Chain of `atempo` filters reaching a positive `factor`, one filter only goes from 0.5 to 2
+pub fn atempo_chain(factor: f64) -> String {
+    let mut filters = Vec::new();
+    let mut rest = factor;
+    while rest > 2.0 {
+        filters.push("atempo=2".to_owned());
+        rest /= 2.0;
+    }
+    while rest < 0.5 {
+        filters.push("atempo=0.5".to_owned());
+        rest *= 2.0;
+    }
+    filters.push(format!("atempo={}", rest));
+    filters.join(",")
+}
+
+/// Escapes a value for a filter option inside a filtergraph,
+/// once for the option parser and once for the graph parser
+pub fn escape_filter_value(value: &str) -> String {
+    let option = value.replace('\\', "\\\\").replace(':', "\\:").replace('\'', "\\'");
+    let mut graph = String::with_capacity(option.len());
+    for c in option.chars() {
+        if matches!(c, '\\' | '\'' | '[' | ']' | ',' | ';') {
+            graph.push('\\');
+        }
+        graph.push(c);
+    }
+    graph
+}
+
+/// Splits text into lines of at most `width` characters, only breaking words longer than a line
+pub fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        let line_len = line.chars().count();
+        if line_len > 0 && line_len + 1 + word.len() <= width {
+            line.push(' ');
+            line.extend(word);
+            continue;
+        }
+        if line_len > 0 {
+            lines.push(std::mem::take(&mut line));
+        }
+        while word.len() > width {
+            lines.push(word.drain(..width).collect());
+        }
+        line = word.into_iter().collect();
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Reader passing data through while computing its size and sha256
+pub struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl<R: AsyncRead + Unpin> HashingReader<R> {
+    pub fn new(inner: R) -> Self {
+        HashingReader { inner, hasher: Sha256::new(), size: 0 }
+    }
+
+    /// Size and hex encoded sha256 of everything read so far
+    pub fn finish(self) -> (u64, String) {
+        (self.size, format!("{:x}", self.hasher.finalize()))
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for HashingReader<R> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let res = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = res {
+            let read = &buf.filled()[before..];
+            self.size += read.len() as u64;
+            self.hasher.update(read);
+        }
+        res
+    }
+}
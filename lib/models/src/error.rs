@@ -14,10 +14,193 @@ pub enum EncodeToSize {
     TargetSizeTooSmall,
 }
 
+#[derive(Error, Serialize, Deserialize, Debug)]
+pub enum Input {
+    #[error("Unsupported URL")]
+    UnsupportedURI,
+    #[error("Path not allowed: {0}")]
+    PathNotAllowed(String),
+    #[error("Input not found: {0}")]
+    NotFound(String),
+    #[error("Input too large: {0} bytes")]
+    TooLarge(u64),
+    #[error("Input download error: {0}")]
+    Download(String),
+    #[error("Can't read input: {0}")]
+    Probe(String),
+    #[error("Playlists can't be used as input: {0}")]
+    Playlist(String),
+}
+
+#[derive(Error, Serialize, Deserialize, Debug)]
+pub enum Combine {
+    #[error("Unsupported output kind")]
+    UnsupportedOutputKind,
+}
+
+#[derive(Error, Serialize, Deserialize, Debug)]
+pub enum Remux {
+    #[error("No stream can be put in this container")]
+    NoCompatibleStream,
+}
+
+#[derive(Error, Serialize, Deserialize, Debug)]
+pub enum Transform {
+    #[error("No video stream")]
+    NoVideoStream,
+    #[error("Crop outside of the video")]
+    InvalidCrop,
+    #[error("Invalid scale")]
+    InvalidScale,
+    #[error("Invalid color: {0}")]
+    InvalidColor(String),
+    #[error("No crop detected")]
+    CropDetect,
+}
+
+#[derive(Error, Serialize, Deserialize, Debug)]
+pub enum Animation {
+    #[error("No video stream")]
+    NoVideoStream,
+    #[error("Target size too small")]
+    TargetSizeTooSmall,
+}
+
+#[derive(Error, Serialize, Deserialize, Debug)]
+pub enum ExtractAudio {
+    #[error("No audio stream with id {0}")]
+    StreamNotFound(usize),
+    #[error("Not an audio container")]
+    UnsupportedContainer,
+}
+
+#[derive(Error, Serialize, Deserialize, Debug)]
+pub enum Loudness {
+    #[error("No audio stream")]
+    NoAudioStream,
+    #[error("Audio is silent")]
+    Silent,
+    #[error("Can't parse the loudness measurement")]
+    Measurement,
+}
+
+#[derive(Error, Serialize, Deserialize, Debug)]
+pub enum Concat {
+    #[error("At least two inputs are needed")]
+    NotEnoughInputs,
+    #[error("No audio or video stream")]
+    NoStream,
+}
+
+#[derive(Error, Serialize, Deserialize, Debug)]
+pub enum Overlay {
+    #[error("No video stream")]
+    NoVideoStream,
+    #[error("Empty text")]
+    EmptyText,
+    #[error("Invalid color: {0}")]
+    InvalidColor(String),
+    #[error("No font file found for {0}")]
+    FontNotFound(String),
+}
+
+#[derive(Error, Serialize, Deserialize, Debug)]
+pub enum Subtitle {
+    #[error("Subtitle stream {0} not found")]
+    StreamNotFound(usize),
+    #[error("Image based subtitles can't be converted to text")]
+    BitmapSubtitle,
+    #[error("No video stream")]
+    NoVideoStream,
+    #[error("No compatible stream for the container")]
+    NoCompatibleStream,
+}
+
+#[derive(Error, Serialize, Deserialize, Debug)]
+pub enum Image {
+    #[error("No video stream")]
+    NoVideoStream,
+    #[error("Timestamp after the end of the video")]
+    OutOfRange,
+}
+
+#[derive(Error, Serialize, Deserialize, Debug)]
+pub enum Reverse {
+    #[error("No stream to reverse")]
+    NoStream,
+    #[error("Input too long to be reversed")]
+    TooLong,
+}
+
+#[derive(Error, Serialize, Deserialize, Debug)]
+pub enum Fade {
+    #[error("No audio or video stream")]
+    NoStream,
+}
+
+#[derive(Error, Serialize, Deserialize, Debug)]
+pub enum Cut {
+    #[error("Nothing left to keep")]
+    Empty,
+    #[error("No audio or video stream")]
+    NoStream,
+}
+
+#[derive(Error, Serialize, Deserialize, Debug)]
+pub enum Split {
+    #[error("No stream the container can hold")]
+    NoStream,
+    #[error("More than {0} parts")]
+    TooManyParts(usize),
+}
+
+#[derive(Error, Serialize, Deserialize, Debug)]
+pub enum Trim {
+    #[error("Nothing to detect in the streams")]
+    NoStream,
+    #[error("No dead air found")]
+    NothingDetected,
+}
+
+#[derive(Error, Serialize, Deserialize, Debug)]
+pub enum Scene {
+    #[error("No video stream")]
+    NoVideoStream,
+    #[error("No scene change found")]
+    NothingDetected,
+}
+
+#[derive(Error, Serialize, Deserialize, Debug)]
+pub enum Speed {
+    #[error("Invalid speed factor")]
+    InvalidFactor,
+    #[error("Slow motion needs a factor under 1")]
+    NotSlower,
+    #[error("No stream")]
+    NoStream,
+}
+
 #[derive(Error, Serialize, Deserialize, Debug)]
 #[error("Encode error: {0}")]
 pub enum Encode {
     EncodeToSize(EncodeToSize),
+    Combine(Combine),
+    Remux(Remux),
+    Transform(Transform),
+    Animation(Animation),
+    ExtractAudio(ExtractAudio),
+    Loudness(Loudness),
+    Concat(Concat),
+    Overlay(Overlay),
+    Subtitle(Subtitle),
+    Image(Image),
+    Reverse(Reverse),
+    Fade(Fade),
+    Cut(Cut),
+    Split(Split),
+    Trim(Trim),
+    Scene(Scene),
+    Speed(Speed),
 }
 
 
@@ -79,6 +262,30 @@ pub enum Ffmpeg {
     },
 }
 
+#[derive(Snafu, Debug)]
+#[snafu(visibility(pub))]
+pub enum Storage {
+    StorageS3 {
+        source: S3Error,
+    },
+    StorageIo {
+        source: std::io::Error,
+    },
+    InvalidKey {
+        key: String,
+    },
+    NotFound {
+        key: String,
+    },
+    PresignUnsupported,
+    UnexpectedStatus {
+        status: u16,
+    },
+    Metadata {
+        key: String,
+    },
+}
+
 #[derive(Snafu, Debug)]
 #[snafu(visibility(pub))]
 pub enum Worker {
@@ -87,6 +294,11 @@ pub enum Worker {
         backtrace: snafu::Backtrace,
         location: snafu::Location,
     },
+    Input {
+        source: Input,
+        backtrace: snafu::Backtrace,
+        location: snafu::Location,
+    },
     Ffmpeg {
         source: Ffmpeg,
         backtrace: snafu::Backtrace,
@@ -110,6 +322,11 @@ pub enum Worker {
         backtrace: snafu::Backtrace,
         location: snafu::Location,
     },
+    Storage {
+        source: Storage,
+        backtrace: snafu::Backtrace,
+        location: snafu::Location,
+    },
 }
 
 #[derive(Error, Debug)]
@@ -131,6 +348,7 @@ pub enum Interaction {
     InvalidInput(InvalidInput),
     Redis(redis::RedisError),
     S3(S3Error),
+    Storage(Storage),
     Serde(serde_json::Error),
     Chrono(Chrono)
 }
@@ -171,6 +389,12 @@ impl From<S3Error> for Interaction {
     }
 }
 
+impl From<Storage> for Interaction {
+    fn from(error: Storage) -> Self {
+        Interaction::Storage(error)
+    }
+}
+
 impl From<chrono::OutOfRangeError> for Interaction {
     fn from(error: chrono::OutOfRangeError) -> Self {
         Interaction::Chrono(Chrono::OutOfRange(error))
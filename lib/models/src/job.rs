@@ -3,7 +3,60 @@ use crate::*;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Response {
-    GetStreams(Vec::<MediaStream>)
+    GetStreams(Vec::<MediaStream>),
+    /// Bounds the kept ranges were snapped to, sent before the result of a keyframe cut
+    Cut(Vec<CutSegment>),
+    DeadAir(Vec<DeadAir>),
+    /// Timestamps the scenes after the first one start at
+    Scenes(Vec<std::time::Duration>),
+}
+
+/// A file produced by a job, waiting in the storage to be sent
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Output {
+    pub key: String,
+    /// Name it is sent with
+    pub filename: String,
+    pub mime_type: String,
+    /// Size in bytes
+    pub size: u64,
+}
+
+impl Output {
+    pub fn new(key: String, filename: String, size: u64) -> Output {
+        let mime_type = get_mime_type(&filename).to_owned();
+        Output { key, filename, mime_type, size }
+    }
+}
+
+/// MIME type of a file from its extension
+pub fn get_mime_type(filename: &str) -> &'static str {
+    let extension = std::path::Path::new(filename)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    match extension.as_str() {
+        "mp4" => "video/mp4",
+        "mkv" => "video/x-matroska",
+        "webm" => "video/webm",
+        "mov" => "video/quicktime",
+        "gif" => "image/gif",
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "mka" => "audio/x-matroska",
+        "ogg" => "audio/ogg",
+        "opus" => "audio/opus",
+        "wav" => "audio/wav",
+        "flac" => "audio/flac",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "webp" => "image/webp",
+        "srt" => "application/x-subrip",
+        "vtt" => "text/vtt",
+        "ass" => "text/x-ssa",
+        _ => "application/octet-stream",
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -12,7 +65,12 @@ pub enum Progress {
     Progress(f32),
     Error(String),
     Response(job::Response),
-    Done(String),
+    Done {
+        /// Every file the job produced, in the order they are sent
+        outputs: Vec<Output>,
+        /// The output was served from the result cache instead of being encoded
+        cache_hit: bool,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -21,7 +79,7 @@ pub enum Kind {
     Parsing,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 
 pub enum Parameters {
     EncodeToSize(EncodeToSizeParameters),
@@ -29,7 +87,32 @@ pub enum Parameters {
     Remux(RemuxParameters),
     GetStreams,
     Combine(CombineParameters),
-    Speed(SpeedParameters)
+    Speed(SpeedParameters),
+    Transform(TransformParameters),
+    Orient(OrientParameters),
+    Animation(AnimationParameters),
+    ExtractAudio(ExtractAudioParameters),
+    Loudness(LoudnessParameters),
+    Concat(ConcatParameters),
+    Overlay(OverlayParameters),
+    Subtitle(SubtitleParameters),
+    Image(ImageParameters),
+    Reverse(ReverseParameters),
+    Fade(FadeParameters),
+    Split(SplitParameters),
+    Trim(TrimParameters),
+    /// Only reports what a trim would remove
+    DetectDeadAir(TrimParameters),
+    Scenes(SceneParameters),
+    /// Only reports the scene changes found with this threshold
+    DetectScenes(f64),
+}
+
+impl Parameters {
+    /// The result cache only holds jobs with a single output
+    pub fn has_single_output(&self) -> bool {
+        !matches!(self, Parameters::Split(_) | Parameters::Scenes(SceneParameters { action: SceneAction::Split, .. }))
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -3,45 +3,899 @@ use serde::{Deserialize, Serialize};
 pub mod job;
 pub mod error;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct EncodeToSizeParameters {
     pub target_size: u32,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CutParameters {
+    pub segments: Vec<CutSegment>,
+    /// The segments are taken out and the rest of the input is kept
+    pub remove: bool,
+    pub mode: CutMode,
+}
+
+/// Range of the input, from its start and to its end when unset
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct CutSegment {
     pub start: Option<std::time::Duration>,
     pub end: Option<std::time::Duration>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum CutMode {
+    /// Stream copy, the start of each range moves back to the previous keyframe
+    Keyframe,
+    /// Only the frames between a bound and the nearest keyframe are re-encoded
+    Smart,
+    /// The whole result is re-encoded
+    Reencode,
+}
+
+impl CutMode {
+    pub const ALL: [CutMode; 3] = [CutMode::Keyframe, CutMode::Smart, CutMode::Reencode];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            CutMode::Keyframe => "Rapide (images clés)",
+            CutMode::Smart => "Précis (ré-encode les bords)",
+            CutMode::Reencode => "Précis (ré-encode tout)",
+        }
+    }
+}
+
+impl CutParameters {
+    /// Ranges kept from an input of `duration` seconds, sorted and merged, in seconds
+    pub fn get_kept_ranges(&self, duration: f64) -> Vec<(f64, f64)> {
+        let mut ranges: Vec<(f64, f64)> = self.segments.iter()
+            .map(|s| (
+                s.start.map_or(0.0, |d| d.as_secs_f64()).min(duration),
+                s.end.map_or(duration, |d| d.as_secs_f64()).min(duration),
+            ))
+            .filter(|(start, end)| start < end)
+            .collect();
+        ranges.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut merged: Vec<(f64, f64)> = Vec::new();
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        if !self.remove {
+            return merged;
+        }
+        let mut kept = Vec::new();
+        let mut position = 0.0;
+        for (start, end) in merged {
+            if start > position {
+                kept.push((position, start));
+            }
+            position = end;
+        }
+        if position < duration {
+            kept.push((position, duration));
+        }
+        kept
+    }
+
+    /// Container of the result, the input one when the streams are copied
+    pub fn get_container(&self, input: Option<VideoContainer>) -> VideoContainer {
+        match self.mode {
+            CutMode::Keyframe => input.unwrap_or(VideoContainer::MKV),
+            CutMode::Smart | CutMode::Reencode => VideoContainer::get_reencode_container(input),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RemuxParameters {
     pub container: VideoContainer,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CombineVideo {
-    pub url: String,
+    pub url: VideoURI,
     pub selected_streams: Vec<usize>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CombineParameters {
     pub videos: Vec<CombineVideo>,
     pub output_kind: StreamKind,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SpeedParameters {
     pub speed_factor: f64,
+    pub mode: SpeedMode,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum SpeedMode {
+    /// Audio tempo follows the video, the pitch is kept
+    KeepPitch,
+    /// Pitch follows the speed, like a tape played faster
+    Chipmunk,
+    /// Pitch changes by the factor, the duration is kept and the video copied
+    PitchShift,
+    /// Only the audio is re-encoded, the video timestamps are rescaled and copied where possible
+    AudioOnly,
+    /// Slowed down video keeping its frame rate, by repeating or interpolating frames
+    SlowMotion { interpolate: bool },
+}
+
+impl SpeedMode {
+    pub const ALL: [SpeedMode; 6] = [
+        SpeedMode::KeepPitch,
+        SpeedMode::Chipmunk,
+        SpeedMode::PitchShift,
+        SpeedMode::AudioOnly,
+        SpeedMode::SlowMotion { interpolate: false },
+        SpeedMode::SlowMotion { interpolate: true },
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            SpeedMode::KeepPitch => "Garder la hauteur du son",
+            SpeedMode::Chipmunk => "Voix de chipmunk",
+            SpeedMode::PitchShift => "Changer la hauteur seulement",
+            SpeedMode::AudioOnly => "Rapide (ré-encode le son seulement)",
+            SpeedMode::SlowMotion { interpolate: false } => "Ralenti (images répétées)",
+            SpeedMode::SlowMotion { interpolate: true } => "Ralenti (images interpolées)",
+        }
+    }
+}
+
+/// Applied in order: crop, then scale, then pad
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TransformParameters {
+    pub crop: Option<Crop>,
+    pub scale: Option<Scale>,
+    pub pad: Option<Pad>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Crop {
+    Rect { x: u32, y: u32, width: u32, height: u32 },
+    /// Remove the black borders found by cropdetect
+    Auto,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Scale {
+    /// A missing side is computed to keep the aspect ratio
+    Size { width: Option<u32>, height: Option<u32> },
+    Percent(u32),
+}
+
+impl Scale {
+    /// Output size of a `width`x`height` input, rounded to even sizes for the encoders
+    pub fn get_size(&self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let (width, height) = (width as u64, height as u64);
+        let (w, h) = match self {
+            Scale::Size { width: Some(w), height: Some(h) } => (*w as u64, *h as u64),
+            Scale::Size { width: Some(w), height: None } => (*w as u64, height * *w as u64 / width.max(1)),
+            Scale::Size { width: None, height: Some(h) } => (width * *h as u64 / height.max(1), *h as u64),
+            Scale::Size { width: None, height: None } => return None,
+            Scale::Percent(p) => (width * *p as u64 / 100, height * *p as u64 / 100),
+        };
+        if w == 0 || h == 0 || w > 8192 || h > 8192 {
+            return None;
+        }
+        Some((round_even(w), round_even(h)))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Pad {
+    pub aspect_ratio: AspectRatio,
+    pub fill: PadFill,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum AspectRatio {
+    /// 9:16, for shorts
+    Portrait,
+    Square,
+    /// 16:9
+    Landscape,
+}
+
+impl AspectRatio {
+    pub const ALL: [AspectRatio; 3] = [AspectRatio::Portrait, AspectRatio::Square, AspectRatio::Landscape];
+
+    pub fn get_ratio(&self) -> (u32, u32) {
+        match self {
+            AspectRatio::Portrait => (9, 16),
+            AspectRatio::Square => (1, 1),
+            AspectRatio::Landscape => (16, 9),
+        }
+    }
+
+    pub fn get_name(&self) -> String {
+        let (w, h) = self.get_ratio();
+        format!("{w}:{h}")
+    }
+
+    /// Padded frames are kept under this short side so players can decode them
+    pub const MAX_SHORT_SIDE: u32 = 1080;
+
+    /// Smallest even size with this ratio that contains a `width`x`height` frame, and the size
+    /// the frame is scaled to in it, both scaled down when the short side is over [`Self::MAX_SHORT_SIDE`]
+    pub fn fit(&self, width: u32, height: u32) -> ((u32, u32), (u32, u32)) {
+        let (rw, rh) = self.get_ratio();
+        let (width, height, rw, rh) = (width as u64, height as u64, rw as u64, rh as u64);
+        let (w, h) = if width * rh >= height * rw {
+            (width, (width as f64 * rh as f64 / rw as f64).ceil() as u64)
+        } else {
+            ((height as f64 * rw as f64 / rh as f64).ceil() as u64, height)
+        };
+        let max = Self::MAX_SHORT_SIDE as u64;
+        if w.min(h) <= max {
+            return ((round_even(w), round_even(h)), (width as u32, height as u32));
+        }
+
+        let (w, h) = match rw <= rh {
+            true => (max, max * rh / rw),
+            false => (max * rw / rh, max),
+        };
+        let scale = (w as f64 / width as f64).min(h as f64 / height as f64);
+        // Rounded down so the scaled frame stays inside the padded one
+        let floor_even = |x: u64| ((x as f64 * scale) as u32 & !1).max(2);
+        ((round_even(w), round_even(h)), (floor_even(width), floor_even(height)))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum PadFill {
+    /// Blurred and enlarged copy of the video
+    Blur,
+    /// ffmpeg color name or hex code, ex. `black` or `#1e1e1e`
+    Color(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OrientParameters {
+    pub orientation: Orientation,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Orientation {
+    Rotate(Rotation),
+    FlipHorizontal,
+    FlipVertical,
+    /// Applies the rotation metadata to the pixels, for players ignoring it
+    Bake,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Rotation {
+    Clockwise,
+    UpsideDown,
+    CounterClockwise,
+}
+
+impl Rotation {
+    /// Clockwise angle
+    pub fn get_degrees(&self) -> u32 {
+        match self {
+            Rotation::Clockwise => 90,
+            Rotation::UpsideDown => 180,
+            Rotation::CounterClockwise => 270,
+        }
+    }
+}
+
+impl OrientParameters {
+    /// Container of an input whose rotation can be changed by editing its metadata only,
+    /// `None` if the video has to be re-encoded
+    pub fn get_metadata_container(&self, input: Option<VideoContainer>) -> Option<VideoContainer> {
+        match (self.orientation, input) {
+            (Orientation::Rotate(_), Some(c @ (VideoContainer::MP4 | VideoContainer::MOV))) => Some(c),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AnimationParameters {
+    pub format: AnimationFormat,
+    pub fps: u32,
+    /// Source width if `None`, the height keeps the aspect ratio
+    pub width: Option<u32>,
+    /// Number of times the animation is played, 0 loops forever
+    pub loops: u32,
+    /// Only used by gifs
+    pub dither: Dither,
+    /// Max output size in bytes, fps and width are lowered until the output fits
+    pub target_size: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum AnimationFormat {
+    Gif,
+    WebP,
+    APng,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl AnimationFormat {
+    pub const ALL: [AnimationFormat; 3] = [AnimationFormat::Gif, AnimationFormat::WebP, AnimationFormat::APng];
+
+    pub fn get_file_extension(&self) -> String {
+        match self {
+            AnimationFormat::Gif => "gif",
+            AnimationFormat::WebP => "webp",
+            AnimationFormat::APng => "png",
+        }.to_owned()
+    }
+
+    /// Name of the ffmpeg muxer
+    pub fn get_format(&self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => "gif",
+            AnimationFormat::WebP => "webp",
+            AnimationFormat::APng => "apng",
+        }
+    }
+}
+
+/// Dithering used when reducing a gif to its palette
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Dither {
+    None,
+    Bayer,
+    FloydSteinberg,
+    Sierra2_4a,
+}
+
+impl Dither {
+    /// Name of the paletteuse dither mode
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Dither::None => "none",
+            Dither::Bayer => "bayer",
+            Dither::FloydSteinberg => "floyd_steinberg",
+            Dither::Sierra2_4a => "sierra2_4a",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExtractAudioParameters {
+    /// `MediaStream` id of the audio stream
+    pub stream: usize,
+    /// Copied when it accepts the codec, transcoded otherwise
+    pub container: VideoContainer,
+    /// Bits per second of lossy transcodes
+    pub bit_rate: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LoudnessParameters {
+    pub adjustment: VolumeAdjustment,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum VolumeAdjustment {
+    /// Two pass EBU R128 normalization
+    Normalize(LoudnessTarget),
+    /// Fixed gain in dB
+    Gain(f64),
+    Mute,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct LoudnessTarget {
+    /// Integrated loudness in LUFS
+    pub integrated: f64,
+    /// Max true peak in dBTP
+    pub true_peak: f64,
+    /// Loudness range in LU
+    pub range: f64,
+}
+
+impl LoudnessTarget {
+    /// What streaming platforms normalize to
+    pub const STREAMING: LoudnessTarget = LoudnessTarget { integrated: -14.0, true_peak: -1.0, range: 11.0 };
+    /// EBU R128 broadcast
+    pub const BROADCAST: LoudnessTarget = LoudnessTarget { integrated: -23.0, true_peak: -1.0, range: 7.0 };
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConcatParameters {
+    /// Played one after another, in order
+    pub videos: Vec<VideoURI>,
+    /// Between each clip, they are joined directly otherwise
+    pub transition: Option<Transition>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Transition {
+    pub kind: TransitionKind,
+    pub duration: std::time::Duration,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TransitionKind {
+    Fade,
+    FadeBlack,
+    Dissolve,
+    WipeLeft,
+    SlideLeft,
+    CircleOpen,
+    Pixelize,
+}
+
+impl TransitionKind {
+    pub const ALL: [TransitionKind; 7] = [
+        TransitionKind::Fade,
+        TransitionKind::FadeBlack,
+        TransitionKind::Dissolve,
+        TransitionKind::WipeLeft,
+        TransitionKind::SlideLeft,
+        TransitionKind::CircleOpen,
+        TransitionKind::Pixelize,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            TransitionKind::Fade => "Fondu enchaîné",
+            TransitionKind::FadeBlack => "Fondu au noir",
+            TransitionKind::Dissolve => "Dissolution",
+            TransitionKind::WipeLeft => "Balayage",
+            TransitionKind::SlideLeft => "Glissement",
+            TransitionKind::CircleOpen => "Ouverture en cercle",
+            TransitionKind::Pixelize => "Pixelisation",
+        }
+    }
+
+    /// Name of the xfade transition
+    pub fn get_filter_name(&self) -> &'static str {
+        match self {
+            TransitionKind::Fade => "fade",
+            TransitionKind::FadeBlack => "fadeblack",
+            TransitionKind::Dissolve => "dissolve",
+            TransitionKind::WipeLeft => "wipeleft",
+            TransitionKind::SlideLeft => "slideleft",
+            TransitionKind::CircleOpen => "circleopen",
+            TransitionKind::Pixelize => "pixelize",
+        }
+    }
+}
+
+/// Fades from and to black and silence
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FadeParameters {
+    pub fade_in: Option<std::time::Duration>,
+    pub fade_out: Option<std::time::Duration>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OverlayParameters {
+    pub overlay: Overlay,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Overlay {
+    Text(TextOverlay),
+    /// Image from another attachment
+    Image(ImageOverlay),
+    /// Caption in a white bar above the video
+    Meme { text: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TextOverlay {
+    pub text: String,
+    pub position: Position,
+    pub font: Font,
+    /// Percentage of the video height
+    pub size: u32,
+    pub color: String,
+    /// Semi transparent box behind the text
+    pub box_color: Option<String>,
+    pub outline: bool,
+    pub start: Option<std::time::Duration>,
+    pub end: Option<std::time::Duration>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImageOverlay {
+    pub url: VideoURI,
+    pub position: Position,
+    /// Between 0 and 1
+    pub opacity: f64,
+    /// Percentage of the video width
+    pub width: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Position {
+    TopLeft,
+    Top,
+    TopRight,
+    Center,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Position {
+    pub const ALL: [Position; 7] = [
+        Position::TopLeft,
+        Position::Top,
+        Position::TopRight,
+        Position::Center,
+        Position::BottomLeft,
+        Position::Bottom,
+        Position::BottomRight,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Position::TopLeft => "En haut à gauche",
+            Position::Top => "En haut",
+            Position::TopRight => "En haut à droite",
+            Position::Center => "Au centre",
+            Position::BottomLeft => "En bas à gauche",
+            Position::Bottom => "En bas",
+            Position::BottomRight => "En bas à droite",
+        }
+    }
+
+    /// ffmpeg `x` and `y` expressions placing an item of size `item_w`x`item_h`
+    /// in a frame of size `frame_w`x`frame_h`, `margin` pixels away from the edges
+    pub fn get_coordinates(&self, item_w: &str, item_h: &str, frame_w: &str, frame_h: &str, margin: u32) -> (String, String) {
+        let left = margin.to_string();
+        let center_x = format!("({frame_w}-{item_w})/2");
+        let right = format!("{frame_w}-{item_w}-{margin}");
+        let top = margin.to_string();
+        let center_y = format!("({frame_h}-{item_h})/2");
+        let bottom = format!("{frame_h}-{item_h}-{margin}");
+        match self {
+            Position::TopLeft => (left, top),
+            Position::Top => (center_x, top),
+            Position::TopRight => (right, top),
+            Position::Center => (center_x, center_y),
+            Position::BottomLeft => (left, bottom),
+            Position::Bottom => (center_x, bottom),
+            Position::BottomRight => (right, bottom),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Font {
+    Sans,
+    Serif,
+    Mono,
+    Impact,
+}
+
+impl Font {
+    pub const ALL: [Font; 4] = [Font::Sans, Font::Serif, Font::Mono, Font::Impact];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Font::Sans => "Sans",
+            Font::Serif => "Serif",
+            Font::Mono => "Mono",
+            Font::Impact => "Impact",
+        }
+    }
+
+    /// Font files to look for with their family name, by order of preference
+    pub fn get_files(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Font::Sans => &[("DejaVuSans-Bold.ttf", "DejaVu Sans")],
+            Font::Serif => &[("DejaVuSerif-Bold.ttf", "DejaVu Serif")],
+            Font::Mono => &[("DejaVuSansMono-Bold.ttf", "DejaVu Sans Mono")],
+            Font::Impact => &[
+                ("impact.ttf", "Impact"),
+                ("Impact.ttf", "Impact"),
+                ("Anton-Regular.ttf", "Anton"),
+                ("DejaVuSans-Bold.ttf", "DejaVu Sans"),
+            ],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SubtitleParameters {
+    pub operation: SubtitleOperation,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum SubtitleOperation {
+    /// Also converts an attached file to another format
+    Extract { source: SubtitleSource, format: SubtitleFormat },
+    Burn { source: SubtitleSource, style: SubtitleStyle },
+    /// Adds the file as a soft track
+    Mux { file: SubtitleFile, container: VideoContainer, language: Option<String> },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum SubtitleSource {
+    /// Subtitle stream of the video
+    Stream(usize),
+    File(SubtitleFile),
+}
+
+/// Attached subtitle file, its filename gives the format
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SubtitleFile {
+    pub url: VideoURI,
+    pub filename: String,
+}
+
+impl SubtitleParameters {
+    pub fn get_file_extension(&self) -> String {
+        match &self.operation {
+            SubtitleOperation::Extract { format, .. } => format.get_file_extension(),
+            SubtitleOperation::Burn { .. } => "mp4".to_owned(),
+            SubtitleOperation::Mux { container, .. } => container.get_file_extension(),
+        }
+    }
+
+    pub fn get_file_mut(&mut self) -> Option<&mut SubtitleFile> {
+        match &mut self.operation {
+            SubtitleOperation::Extract { source: SubtitleSource::File(file), .. }
+            | SubtitleOperation::Burn { source: SubtitleSource::File(file), .. }
+            | SubtitleOperation::Mux { file, .. } => Some(file),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+    Ass,
+}
+
+impl SubtitleFormat {
+    pub const ALL: [SubtitleFormat; 3] = [SubtitleFormat::Srt, SubtitleFormat::Vtt, SubtitleFormat::Ass];
+
+    pub fn get_file_extension(&self) -> String {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Vtt => "vtt",
+            SubtitleFormat::Ass => "ass",
+        }.to_owned()
+    }
+
+    pub fn from_file_extension(extension: &str) -> Option<SubtitleFormat> {
+        SubtitleFormat::ALL.into_iter().find(|f| f.get_file_extension() == extension)
+    }
+
+    /// Name of the ffmpeg muxer, which is also the name of the encoder
+    pub fn get_format(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Vtt => "webvtt",
+            SubtitleFormat::Ass => "ass",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SubtitleStyle {
+    pub font: Font,
+    /// In libass units, relative to a 288 pixels high video
+    pub size: u32,
+    /// RGB
+    pub color: [u8; 3],
+    /// Outlined text, otherwise an opaque box
+    pub outline: bool,
+    pub top: bool,
+}
+
+impl SubtitleStyle {
+    /// `force_style` of the subtitles filter, colors are written as `&HAABBGGRR`
+    pub fn get_force_style(&self, family: &str) -> String {
+        let [r, g, b] = self.color;
+        format!(
+            "FontName={family},FontSize={},PrimaryColour=&H00{b:02X}{g:02X}{r:02X},BorderStyle={},Outline={},Shadow=0,Alignment={}",
+            self.size,
+            if self.outline { 1 } else { 3 },
+            if self.outline { 2 } else { 1 },
+            if self.top { 8 } else { 2 },
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImageParameters {
+    pub kind: ImageKind,
+    pub format: ImageFormat,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum ImageKind {
+    Frame(std::time::Duration),
+    /// Most representative frame, picked by the thumbnail filter
+    Thumbnail,
+    /// Grid of frames taken at regular intervals, with their timestamps
+    ContactSheet { columns: u32, rows: u32 },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    WebP,
+}
+
+impl ImageFormat {
+    pub const ALL: [ImageFormat; 3] = [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::WebP];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "PNG",
+            ImageFormat::Jpeg => "JPEG",
+            ImageFormat::WebP => "WebP",
+        }
+    }
+
+    pub fn get_file_extension(&self) -> String {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::WebP => "webp",
+        }.to_owned()
+    }
+
+    pub fn get_encoder(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "mjpeg",
+            ImageFormat::WebP => "libwebp",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReverseParameters {
+    pub mode: ReverseMode,
+    /// Plays the clip forward before the reversed part
+    pub boomerang: bool,
+    /// Times the result is repeated
+    pub loops: u32,
+}
+
+/// Streams played backward, the others keep playing forward
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ReverseMode {
+    Video,
+    Audio,
+    Both,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SplitParameters {
+    pub mode: SplitMode,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum SplitMode {
+    /// Number of parts of equal length
+    Parts(u32),
+    /// Length of every part, the last one is shorter
+    Length(std::time::Duration),
+    /// Size in bytes every part stays under, as much as the keyframes allow
+    Size(u64),
+    /// Timestamps a new part starts at
+    Timestamps(Vec<std::time::Duration>),
+}
+
+impl SplitParameters {
+    /// Parts are posted one message each
+    pub const MAX_PARTS: usize = 20;
+
+    /// Container of the parts, the streams are copied
+    pub fn get_container(&self, input: Option<VideoContainer>) -> VideoContainer {
+        input.unwrap_or(VideoContainer::MKV)
+    }
+}
+
+/// Dead air to detect, every range found in any of them is removed
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrimParameters {
+    /// Audio quieter than `threshold` dB
+    pub silence: Option<Detection>,
+    /// Frames with pixels darker than `threshold`, from 0 to 1
+    pub black: Option<Detection>,
+    /// Frames differing by less than `threshold` dB of noise from the previous ones
+    pub freeze: Option<Detection>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Detection {
+    pub threshold: f64,
+    /// Shorter ranges are kept
+    pub min_duration: std::time::Duration,
+}
+
+impl Detection {
+    pub const SILENCE: Detection = Detection { threshold: -50.0, min_duration: std::time::Duration::from_secs(1) };
+    pub const BLACK: Detection = Detection { threshold: 0.1, min_duration: std::time::Duration::from_secs(1) };
+    pub const FREEZE: Detection = Detection { threshold: -60.0, min_duration: std::time::Duration::from_secs(1) };
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum DeadAirKind {
+    Silence,
+    Black,
+    Freeze,
+}
+
+impl DeadAirKind {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            DeadAirKind::Silence => "Silence",
+            DeadAirKind::Black => "Image noire",
+            DeadAirKind::Freeze => "Image figée",
+        }
+    }
+}
+
+/// A range of dead air found in the input
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DeadAir {
+    pub kind: DeadAirKind,
+    pub start: std::time::Duration,
+    pub end: std::time::Duration,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SceneParameters {
+    /// Scene change score a frame needs to start a scene, from 0 to 1
+    pub threshold: f64,
+    pub action: SceneAction,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum SceneAction {
+    /// One chapter per scene, the streams are copied
+    Chapters,
+    /// One output per scene
+    Split,
+}
+
+impl SceneParameters {
+    pub const DEFAULT_THRESHOLD: f64 = 0.4;
+
+    /// Container of the result with chapters, the input one when it can hold them
+    pub fn get_container(&self, input: Option<VideoContainer>) -> VideoContainer {
+        match input {
+            Some(c @ (VideoContainer::MKV | VideoContainer::MP4 | VideoContainer::MOV)) => c,
+            _ => VideoContainer::MKV,
+        }
+    }
+}
+
+/// Rounds up to an even size, as needed by yuv420p
+pub fn round_even(x: u64) -> u32 {
+    (x + x % 2).clamp(2, u32::MAX as u64) as u32
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
 pub enum VideoContainer {
     MP3, 
     MP4,
     WEBM,
     MKV,
+    MOV,
+    MKA,
+    OGG,
+    WAV,
+    FLAC,
+    GIF,
+    M4A,
+    OPUS,
 }
 
 
@@ -49,6 +903,7 @@ pub enum VideoContainer {
 pub enum VideoURI {
     Path(String),
     Url(String),
+    S3(String),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -69,35 +924,312 @@ impl Video {
             filename
         }
     }
+
+    /// Container matching the extension of the filename
+    pub fn get_container(&self) -> Option<VideoContainer> {
+        let extension = std::path::Path::new(&self.filename).extension()?.to_str()?;
+        VideoContainer::from_file_extension(&extension.to_lowercase())
+    }
 }
 
 impl VideoContainer {
+    pub const ALL: [VideoContainer; 12] = [
+        VideoContainer::MP4,
+        VideoContainer::MKV,
+        VideoContainer::WEBM,
+        VideoContainer::MOV,
+        VideoContainer::GIF,
+        VideoContainer::MP3,
+        VideoContainer::M4A,
+        VideoContainer::MKA,
+        VideoContainer::OGG,
+        VideoContainer::OPUS,
+        VideoContainer::WAV,
+        VideoContainer::FLAC,
+    ];
+
+    /// Audio only containers, in the order they are picked to copy a codec
+    pub const AUDIO: [VideoContainer; 7] = [
+        VideoContainer::M4A,
+        VideoContainer::OGG,
+        VideoContainer::MP3,
+        VideoContainer::FLAC,
+        VideoContainer::WAV,
+        VideoContainer::OPUS,
+        VideoContainer::MKA,
+    ];
+
     pub fn get_file_extension(&self) -> String {
         match self {
             VideoContainer::MKV => "mkv",
             VideoContainer::MP4 => "mp4",
             VideoContainer::MP3 => "mp3",
             VideoContainer::WEBM => "webm",
+            VideoContainer::MOV => "mov",
+            VideoContainer::MKA => "mka",
+            VideoContainer::OGG => "ogg",
+            VideoContainer::WAV => "wav",
+            VideoContainer::FLAC => "flac",
+            VideoContainer::GIF => "gif",
+            VideoContainer::M4A => "m4a",
+            VideoContainer::OPUS => "opus",
         }.to_owned()
     }
+
+    pub fn from_file_extension(extension: &str) -> Option<VideoContainer> {
+        VideoContainer::ALL.into_iter().find(|c| c.get_file_extension() == extension)
+    }
+
+    /// The input container when it can hold its re-encoded streams, mp4 otherwise
+    pub fn get_reencode_container(input: Option<VideoContainer>) -> VideoContainer {
+        match input {
+            Some(c) if c != VideoContainer::GIF && c.get_default_encoder(&StreamKind::Audio).is_some() => c,
+            _ => VideoContainer::MP4,
+        }
+    }
+
+    /// Name of the ffmpeg muxer
+    pub fn get_format(&self) -> &'static str {
+        match self {
+            VideoContainer::MKV | VideoContainer::MKA => "matroska",
+            VideoContainer::MP4 => "mp4",
+            VideoContainer::MP3 => "mp3",
+            VideoContainer::WEBM => "webm",
+            VideoContainer::MOV => "mov",
+            VideoContainer::OGG => "ogg",
+            VideoContainer::WAV => "wav",
+            VideoContainer::FLAC => "flac",
+            VideoContainer::GIF => "gif",
+            VideoContainer::M4A => "ipod",
+            VideoContainer::OPUS => "opus",
+        }
+    }
+
+    /// Containers that can only hold a single stream
+    pub fn is_single_stream(&self) -> bool {
+        matches!(self, VideoContainer::MP3 | VideoContainer::WAV | VideoContainer::FLAC | VideoContainer::GIF | VideoContainer::OPUS)
+    }
+
+    /// Containers of the mp4 family, they need fragmenting to be written to a pipe
+    pub fn is_mp4_family(&self) -> bool {
+        matches!(self, VideoContainer::MP4 | VideoContainer::MOV | VideoContainer::M4A)
+    }
+
+    /// Encoded without loss, a bit rate doesn't apply
+    pub fn is_lossless(&self) -> bool {
+        matches!(self, VideoContainer::WAV | VideoContainer::FLAC)
+    }
+
+    /// First audio container `codec` can be copied into
+    pub fn from_audio_codec(codec: &str) -> Option<VideoContainer> {
+        VideoContainer::AUDIO.into_iter().find(|c| c.accepts_codec(&StreamKind::Audio, codec))
+    }
+
+    /// Whether a stream of `kind` encoded with `codec` can be stream copied into this container
+    pub fn accepts_codec(&self, kind: &StreamKind, codec: &str) -> bool {
+        let codecs: &[&str] = match (self, kind) {
+            (VideoContainer::MKV, StreamKind::Video | StreamKind::Audio | StreamKind::Subtitle | StreamKind::Attachment) => return true,
+            (VideoContainer::MKA, StreamKind::Audio) => return true,
+            (VideoContainer::MP4, StreamKind::Video) => &["h264", "hevc", "av1", "vp9", "mpeg4"],
+            (VideoContainer::MP4, StreamKind::Audio) => &["aac", "mp3", "opus", "flac", "alac", "ac3", "eac3"],
+            (VideoContainer::MOV, StreamKind::Video) => &["h264", "hevc", "mpeg4", "prores", "mjpeg"],
+            (VideoContainer::MOV, StreamKind::Audio) => &["aac", "mp3", "alac", "ac3", "pcm_s16le", "pcm_s24le"],
+            (VideoContainer::MP4 | VideoContainer::MOV, StreamKind::Subtitle) => &["mov_text"],
+            (VideoContainer::WEBM, StreamKind::Video) => &["vp8", "vp9", "av1"],
+            (VideoContainer::WEBM, StreamKind::Audio) => &["opus", "vorbis"],
+            (VideoContainer::WEBM, StreamKind::Subtitle) => &["webvtt"],
+            (VideoContainer::OGG, StreamKind::Audio) => &["opus", "vorbis", "flac"],
+            (VideoContainer::MP3, StreamKind::Audio) => &["mp3"],
+            (VideoContainer::WAV, StreamKind::Audio) => &["pcm_s16le", "pcm_s24le", "pcm_s32le", "pcm_f32le", "pcm_u8"],
+            (VideoContainer::FLAC, StreamKind::Audio) => &["flac"],
+            (VideoContainer::M4A, StreamKind::Audio) => &["aac", "alac"],
+            (VideoContainer::OPUS, StreamKind::Audio) => &["opus"],
+            (VideoContainer::GIF, StreamKind::Video) => &["gif"],
+            _ => &[],
+        };
+        codecs.contains(&codec)
+    }
+
+    /// Encoder used for streams of `kind` that can't be copied, `None` if the container can't hold them
+    pub fn get_default_encoder(&self, kind: &StreamKind) -> Option<&'static str> {
+        match (self, kind) {
+            (VideoContainer::MP4 | VideoContainer::MOV | VideoContainer::MKV, StreamKind::Video) => Some("libx264"),
+            (VideoContainer::MP4 | VideoContainer::MOV, StreamKind::Subtitle) => Some("mov_text"),
+            (VideoContainer::MP4 | VideoContainer::MOV | VideoContainer::M4A, StreamKind::Audio) => Some("aac"),
+            (VideoContainer::MKV | VideoContainer::MKA | VideoContainer::WEBM | VideoContainer::OGG | VideoContainer::OPUS, StreamKind::Audio) => Some("libopus"),
+            (VideoContainer::MKV, StreamKind::Subtitle) => Some("ass"),
+            (VideoContainer::WEBM, StreamKind::Video) => Some("libvpx-vp9"),
+            (VideoContainer::WEBM, StreamKind::Subtitle) => Some("webvtt"),
+            (VideoContainer::MP3, StreamKind::Audio) => Some("libmp3lame"),
+            (VideoContainer::WAV, StreamKind::Audio) => Some("pcm_s16le"),
+            (VideoContainer::FLAC, StreamKind::Audio) => Some("flac"),
+            (VideoContainer::GIF, StreamKind::Video) => Some("gif"),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum StreamKind {
     Video,
     Audio,
+    Subtitle,
+    Data,
+    Attachment,
     Unknown,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl StreamKind {
+    pub fn get_name(&self) -> &str {
+        match self {
+            StreamKind::Video => "Video",
+            StreamKind::Audio => "Audio",
+            StreamKind::Subtitle => "Subtitle",
+            StreamKind::Data => "Data",
+            StreamKind::Attachment => "Attachment",
+            StreamKind::Unknown => "Unknown",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MediaStream {
     pub id: usize,
     pub kind: StreamKind,
+    /// Duration of the container in microseconds, the same for every stream
     pub duration: i64,
+    pub codec: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<f64>,
+    pub channels: Option<u16>,
+    pub sample_rate: Option<u32>,
+    pub bit_rate: Option<u64>,
+    pub language: Option<String>,
+    /// Clockwise rotation players apply when displaying the video
+    pub rotation: Option<u32>,
+}
+
+impl MediaStream {
+    /// Size of the frames once the rotation metadata is applied
+    pub fn get_display_size(&self) -> Option<(u32, u32)> {
+        match self.rotation {
+            Some(90 | 270) => Some((self.height?, self.width?)),
+            _ => Some((self.width?, self.height?)),
+        }
+    }
+
+    /// Short human readable description, ex. `Audio (opus, 2ch, fr)`
+    pub fn describe(&self) -> String {
+        let mut details = Vec::new();
+        if let Some(codec) = &self.codec {
+            details.push(codec.to_owned());
+        }
+        if let (Some(width), Some(height)) = (self.width, self.height) {
+            details.push(format!("{width}x{height}"));
+        }
+        if let Some(fps) = self.frame_rate {
+            details.push(format!("{}fps", (fps * 100.0).round() / 100.0));
+        }
+        if let Some(channels) = self.channels {
+            details.push(format!("{channels}ch"));
+        }
+        if let Some(language) = &self.language {
+            details.push(language.to_owned());
+        }
+        if details.is_empty() {
+            return self.kind.get_name().to_owned();
+        }
+        format!("{} ({})", self.kind.get_name(), details.join(", "))
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn it_works() { }
+
+    #[test]
+    fn describe_stream() {
+        let stream = MediaStream {
+            id: 1,
+            kind: StreamKind::Audio,
+            duration: 0,
+            codec: Some("opus".to_owned()),
+            width: None,
+            height: None,
+            frame_rate: None,
+            channels: Some(2),
+            sample_rate: Some(48000),
+            bit_rate: Some(128000),
+            language: Some("fr".to_owned()),
+            rotation: None,
+        };
+        assert_eq!(stream.describe(), "Audio (opus, 2ch, fr)");
+    }
+
+    #[test]
+    fn container_extensions() {
+        for container in VideoContainer::ALL {
+            assert_eq!(VideoContainer::from_file_extension(&container.get_file_extension()), Some(container));
+        }
+        assert!(VideoContainer::WEBM.accepts_codec(&StreamKind::Audio, "opus"));
+        assert!(!VideoContainer::WEBM.accepts_codec(&StreamKind::Video, "h264"));
+        assert_eq!(VideoContainer::MP3.get_default_encoder(&StreamKind::Video), None);
+        assert_eq!(VideoContainer::from_audio_codec("aac"), Some(VideoContainer::M4A));
+        assert_eq!(VideoContainer::from_audio_codec("opus"), Some(VideoContainer::OGG));
+        assert_eq!(VideoContainer::from_audio_codec("pcm_s24le"), Some(VideoContainer::WAV));
+    }
+
+    #[test]
+    fn subtitle_style() {
+        let style = SubtitleStyle { font: Font::Sans, size: 24, color: [255, 204, 0], outline: true, top: false };
+        assert_eq!(
+            style.get_force_style("DejaVu Sans"),
+            "FontName=DejaVu Sans,FontSize=24,PrimaryColour=&H0000CCFF,BorderStyle=1,Outline=2,Shadow=0,Alignment=2"
+        );
+        assert_eq!(SubtitleFormat::from_file_extension("vtt"), Some(SubtitleFormat::Vtt));
+    }
+
+    #[test]
+    fn cut_ranges() {
+        let second = std::time::Duration::from_secs;
+        let mut params = CutParameters {
+            segments: vec![
+                CutSegment { start: Some(second(20)), end: Some(second(30)) },
+                CutSegment { start: Some(second(5)), end: Some(second(10)) },
+                CutSegment { start: Some(second(8)), end: Some(second(12)) },
+            ],
+            remove: false,
+            mode: CutMode::Keyframe,
+        };
+        assert_eq!(params.get_kept_ranges(25.0), vec![(5.0, 12.0), (20.0, 25.0)]);
+        params.remove = true;
+        assert_eq!(params.get_kept_ranges(25.0), vec![(0.0, 5.0), (12.0, 20.0)]);
+        params.segments = vec![CutSegment { start: None, end: Some(second(3)) }];
+        assert_eq!(params.get_kept_ranges(25.0), vec![(3.0, 25.0)]);
+    }
+
+    #[test]
+    fn output_manifest() {
+        let output = job::Output::new("outputs/abc-0".to_owned(), "clip-1.MKV".to_owned(), 42);
+        assert_eq!(output.mime_type, "video/x-matroska");
+        assert_eq!(job::get_mime_type("frame.jpg"), "image/jpeg");
+        assert_eq!(job::get_mime_type("noextension"), "application/octet-stream");
+    }
+
+    #[test]
+    fn transform_sizes() {
+        assert_eq!(AspectRatio::Square.fit(640, 480), ((640, 640), (640, 480)));
+        assert_eq!(AspectRatio::Portrait.fit(720, 720), ((720, 1280), (720, 720)));
+        // Bigger frames are scaled down instead of padded to huge sizes
+        assert_eq!(AspectRatio::Portrait.fit(1920, 1080), ((1080, 1920), (1080, 606)));
+        assert_eq!(AspectRatio::Landscape.fit(1080, 1920), ((1920, 1080), (606, 1080)));
+        assert_eq!(AspectRatio::Square.fit(3840, 2160), ((1080, 1080), (1080, 606)));
+        assert_eq!(Scale::Percent(50).get_size(1920, 1080), Some((960, 540)));
+        assert_eq!(Scale::Size { width: Some(1280), height: None }.get_size(1920, 1080), Some((1280, 720)));
+        assert_eq!(Scale::Size { width: None, height: None }.get_size(1920, 1080), None);
+    }
 }
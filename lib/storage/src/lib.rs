@@ -0,0 +1,107 @@
+//! Where the worker puts its outputs and the bot reads them back from
+//!
+//! The backend is chosen with `IVE_STORAGE`: `s3` (default) or `local`, the latter
+//! storing objects in `IVE_STORAGE_PATH` so a single host deployment needs no s3 server.
+
+mod local;
+mod s3;
+
+use std::{path::PathBuf, time::Duration};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use models::error;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncRead;
+
+pub use crate::s3::S3Storage;
+pub use local::LocalStorage;
+
+type Result<T> = std::result::Result<T, error::Storage>;
+
+/// Prefix of the job outputs waiting to be sent by the bot
+pub const OUTPUTS_PREFIX: &str = "outputs/";
+/// Prefix of the outputs kept to answer identical jobs
+pub const CACHE_PREFIX: &str = "cache/";
+
+/// An object as returned by [Storage::list]
+#[derive(Debug, Clone)]
+pub struct StoredObject {
+    pub key: String,
+    pub size: u64,
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+/// Integrity information recorded once an object is fully written
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ObjectMetadata {
+    pub size: u64,
+    /// Hex encoded sha256 of the content
+    pub sha256: String,
+}
+
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Stores everything read from `reader` under `key`
+    async fn put_stream(&self, reader: &mut (dyn AsyncRead + Unpin + Send), key: &str) -> Result<()>;
+    async fn get(&self, key: &str) -> Result<Vec<u8>>;
+    async fn delete(&self, key: &str) -> Result<()>;
+    /// Copies `from` to `to` inside the backend, metadata included
+    async fn copy(&self, from: &str, to: &str) -> Result<()>;
+    /// Time limited url to download `key` without credentials
+    async fn presign(&self, key: &str, expiry: Duration) -> Result<String>;
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>>;
+    async fn set_metadata(&self, key: &str, metadata: &ObjectMetadata) -> Result<()>;
+    /// Metadata of `key`, `None` if it was never set
+    async fn get_metadata(&self, key: &str) -> Result<Option<ObjectMetadata>>;
+
+    /// Whether presigned urls stop working once expired, otherwise they last until
+    /// the object is deleted
+    fn presign_expires(&self) -> bool {
+        true
+    }
+
+    /// Path of `key` if the backend stores objects on the local filesystem
+    fn local_path(&self, _key: &str) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// Key of the output of the job `id`
+pub fn output_key(id: &str) -> String {
+    format!("{OUTPUTS_PREFIX}{id}")
+}
+
+/// Key of the part `index` of the output of the job `id`, for jobs with several outputs
+pub fn output_part_key(id: &str, index: usize) -> String {
+    format!("{OUTPUTS_PREFIX}{id}-{index}")
+}
+
+/// Key of a cached result
+pub fn cache_key(hash: &str) -> String {
+    format!("{CACHE_PREFIX}{hash}")
+}
+
+/// Deletes the objects under `prefix` last modified more than `max_age` ago,
+/// returning how many were deleted
+pub async fn delete_expired(storage: &dyn Storage, prefix: &str, max_age: Duration) -> Result<usize> {
+    let max_age = chrono::Duration::from_std(max_age).unwrap_or(chrono::Duration::max_value());
+    let now = Utc::now();
+    let mut deleted = 0;
+    for object in storage.list(prefix).await? {
+        let Some(last_modified) = object.last_modified else { continue };
+        if now - last_modified > max_age {
+            storage.delete(&object.key).await?;
+            deleted += 1;
+        }
+    }
+    Ok(deleted)
+}
+
+/// Gets the storage backend configured in the environment
+pub fn get_storage() -> Box<dyn Storage> {
+    match config::get_storage_kind().as_str() {
+        "local" => Box::new(LocalStorage::new(config::get_storage_path(), config::get_storage_public_url())),
+        _ => Box::new(S3Storage::new(config::get_s3_bucket())),
+    }
+}
@@ -0,0 +1,295 @@
+use std::{
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use models::error;
+use snafu::ResultExt;
+use tokio::{fs, io::AsyncRead};
+
+use crate::{ObjectMetadata, Result, Storage, StoredObject};
+
+/// Metadata is kept in a file next to the object
+const METADATA_EXTENSION: &str = ".meta";
+
+/// Objects stored as files in a local directory
+pub struct LocalStorage {
+    root: PathBuf,
+    /// Base url the directory is served from, needed to presign
+    public_url: Option<String>,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>, public_url: Option<String>) -> Self {
+        LocalStorage { root: root.into(), public_url }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        // Keys must stay inside the storage directory
+        let valid = !key.is_empty() && Path::new(key).components().all(|c| matches!(c, Component::Normal(_)));
+        if !valid {
+            return Err(error::Storage::InvalidKey { key: key.to_owned() });
+        }
+        Ok(self.root.join(key))
+    }
+
+    fn metadata_path(&self, key: &str) -> Result<PathBuf> {
+        let mut path = self.path(key)?.into_os_string();
+        path.push(METADATA_EXTENSION);
+        Ok(path.into())
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put_stream(&self, reader: &mut (dyn AsyncRead + Unpin + Send), key: &str) -> Result<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await.context(error::StorageIoSnafu)?;
+        }
+
+        // Write next to the destination first so readers never see a partial object
+        let mut tmp_path = path.to_owned().into_os_string();
+        tmp_path.push(".part");
+        let mut file = fs::File::create(&tmp_path).await.context(error::StorageIoSnafu)?;
+        if let Err(e) = tokio::io::copy(reader, &mut file).await {
+            let _ = fs::remove_file(&tmp_path).await;
+            return Err(e).context(error::StorageIoSnafu);
+        }
+        fs::rename(&tmp_path, &path).await.context(error::StorageIoSnafu)?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        match fs::read(self.path(key)?).await {
+            Ok(bytes) => Ok(bytes),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(error::Storage::NotFound { key: key.to_owned() }),
+            Err(e) => Err(e).context(error::StorageIoSnafu),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        for path in [self.path(key)?, self.metadata_path(key)?] {
+            match fs::remove_file(path).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e).context(error::StorageIoSnafu),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    async fn copy(&self, from: &str, to: &str) -> Result<()> {
+        let (source, path) = (self.path(from)?, self.path(to)?);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await.context(error::StorageIoSnafu)?;
+        }
+
+        let mut tmp_path = path.to_owned().into_os_string();
+        tmp_path.push(".part");
+        match fs::copy(&source, &tmp_path).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(error::Storage::NotFound { key: from.to_owned() }),
+            Err(e) => {
+                let _ = fs::remove_file(&tmp_path).await;
+                return Err(e).context(error::StorageIoSnafu);
+            }
+        }
+        fs::rename(&tmp_path, &path).await.context(error::StorageIoSnafu)?;
+
+        // The destination must not keep the metadata of what it replaced
+        let metadata = match fs::copy(self.metadata_path(from)?, self.metadata_path(to)?).await {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => fs::remove_file(self.metadata_path(to)?).await,
+            res => res.map(|_| ()),
+        };
+        match metadata {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e).context(error::StorageIoSnafu),
+            _ => Ok(()),
+        }
+    }
+
+    /// Links are not signed, they stay valid until the file is deleted
+    async fn presign(&self, key: &str, _expiry: Duration) -> Result<String> {
+        self.path(key)?;
+        match &self.public_url {
+            Some(url) => Ok(format!("{}/{}", url.trim_end_matches('/'), key)),
+            None => Err(error::Storage::PresignUnsupported),
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>> {
+        let mut objects = Vec::new();
+        let mut dirs = vec![self.root.to_owned()];
+
+        while let Some(dir) = dirs.pop() {
+            let mut entries = match fs::read_dir(&dir).await {
+                Ok(e) => e,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e).context(error::StorageIoSnafu),
+            };
+            while let Some(entry) = entries.next_entry().await.context(error::StorageIoSnafu)? {
+                let metadata = entry.metadata().await.context(error::StorageIoSnafu)?;
+                if metadata.is_dir() {
+                    dirs.push(entry.path());
+                    continue;
+                }
+                let Ok(key) = entry.path().strip_prefix(&self.root).map(|p| p.to_string_lossy().into_owned()) else { continue };
+                if !key.starts_with(prefix) || key.ends_with(".part") || key.ends_with(METADATA_EXTENSION) {
+                    continue;
+                }
+                objects.push(StoredObject {
+                    key,
+                    size: metadata.len(),
+                    last_modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+                });
+            }
+        }
+        Ok(objects)
+    }
+
+    async fn set_metadata(&self, key: &str, metadata: &ObjectMetadata) -> Result<()> {
+        if !self.path(key)?.exists() {
+            return Err(error::Storage::NotFound { key: key.to_owned() });
+        }
+        let json = serde_json::to_vec(metadata).map_err(|_| error::Storage::Metadata { key: key.to_owned() })?;
+        fs::write(self.metadata_path(key)?, json).await.context(error::StorageIoSnafu)
+    }
+
+    async fn get_metadata(&self, key: &str) -> Result<Option<ObjectMetadata>> {
+        let json = match fs::read(self.metadata_path(key)?).await {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).context(error::StorageIoSnafu),
+        };
+        serde_json::from_slice(&json).map(Some).map_err(|_| error::Storage::Metadata { key: key.to_owned() })
+    }
+
+    fn presign_expires(&self) -> bool {
+        false
+    }
+
+    fn local_path(&self, key: &str) -> Option<PathBuf> {
+        self.path(key).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    use tokio::io::ReadBuf;
+
+    use super::*;
+
+    fn temp_storage(name: &str) -> (LocalStorage, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("storage-local-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        (LocalStorage::new(&dir, Some("https://example.com/files/".to_owned())), dir)
+    }
+
+    /// Sends a few bytes then fails, like a dropped connection
+    struct FailingReader {
+        sent: bool,
+    }
+
+    impl AsyncRead for FailingReader {
+        fn poll_read(mut self: Pin<&mut Self>, _: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+            if self.sent {
+                return Poll::Ready(Err(std::io::ErrorKind::ConnectionReset.into()));
+            }
+            self.sent = true;
+            buf.put_slice(b"partial");
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn objects() {
+        let (storage, dir) = temp_storage("objects");
+        storage.put_stream(&mut b"first".as_slice(), "outputs/a").await.unwrap();
+        storage.put_stream(&mut b"second".as_slice(), "cache/b").await.unwrap();
+        assert_eq!(storage.get("outputs/a").await.unwrap(), b"first");
+
+        let listed = storage.list("outputs/").await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!((listed[0].key.as_str(), listed[0].size), ("outputs/a", 5));
+        assert!(listed[0].last_modified.is_some());
+        assert_eq!(storage.list("").await.unwrap().len(), 2);
+
+        storage.copy("outputs/a", "cache/c").await.unwrap();
+        assert_eq!(storage.get("cache/c").await.unwrap(), b"first");
+        assert!(matches!(storage.copy("outputs/missing", "cache/d").await, Err(error::Storage::NotFound { .. })));
+
+        assert_eq!(storage.presign("outputs/a", Duration::from_secs(60)).await.unwrap(), "https://example.com/files/outputs/a");
+
+        storage.delete("outputs/a").await.unwrap();
+        assert!(matches!(storage.get("outputs/a").await, Err(error::Storage::NotFound { .. })));
+        // Deleting twice is not an error
+        storage.delete("outputs/a").await.unwrap();
+        assert!(storage.list("outputs/").await.unwrap().is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn metadata_sidecar() {
+        let (storage, dir) = temp_storage("metadata");
+        let metadata = ObjectMetadata { size: 5, sha256: "abc".to_owned() };
+        assert!(matches!(storage.set_metadata("outputs/a", &metadata).await, Err(error::Storage::NotFound { .. })));
+
+        storage.put_stream(&mut b"first".as_slice(), "outputs/a").await.unwrap();
+        assert_eq!(storage.get_metadata("outputs/a").await.unwrap(), None);
+        storage.set_metadata("outputs/a", &metadata).await.unwrap();
+        assert!(dir.join("outputs/a.meta").is_file());
+        assert_eq!(storage.get_metadata("outputs/a").await.unwrap(), Some(metadata.to_owned()));
+        // The sidecar is not an object of its own
+        assert_eq!(storage.list("outputs/").await.unwrap().len(), 1);
+
+        // Copies carry the metadata, and don't keep the one of the object they replace
+        storage.copy("outputs/a", "cache/a").await.unwrap();
+        assert_eq!(storage.get_metadata("cache/a").await.unwrap(), Some(metadata));
+        storage.put_stream(&mut b"other".as_slice(), "outputs/b").await.unwrap();
+        storage.copy("outputs/b", "cache/a").await.unwrap();
+        assert_eq!(storage.get_metadata("cache/a").await.unwrap(), None);
+
+        storage.delete("outputs/a").await.unwrap();
+        assert!(!dir.join("outputs/a.meta").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn key_traversal() {
+        let (storage, dir) = temp_storage("traversal");
+        for key in ["", "../secret", "outputs/../../secret", "/etc/passwd", "./outputs/a"] {
+            assert!(matches!(storage.path(key), Err(error::Storage::InvalidKey { .. })), "{key}");
+            assert!(storage.put_stream(&mut b"x".as_slice(), key).await.is_err());
+        }
+        assert_eq!(storage.path("outputs/a").unwrap(), dir.join("outputs/a"));
+        assert!(storage.local_path("../secret").is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn partial_writes() {
+        let (storage, dir) = temp_storage("partial");
+        // A failed write leaves neither the object nor its temporary file
+        assert!(storage.put_stream(&mut FailingReader { sent: false }, "outputs/a").await.is_err());
+        assert!(matches!(storage.get("outputs/a").await, Err(error::Storage::NotFound { .. })));
+        assert!(!dir.join("outputs/a.part").exists());
+
+        // A write in progress is not listed, and replaces the object only once complete
+        storage.put_stream(&mut b"old".as_slice(), "outputs/a").await.unwrap();
+        std::fs::write(dir.join("outputs/a.part"), b"new").unwrap();
+        assert_eq!(storage.list("outputs/").await.unwrap().len(), 1);
+        assert!(storage.put_stream(&mut FailingReader { sent: false }, "outputs/a").await.is_err());
+        assert_eq!(storage.get("outputs/a").await.unwrap(), b"old");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use models::error;
+use s3::Bucket;
+use snafu::ResultExt;
+use tokio::io::AsyncRead;
+
+use crate::{ObjectMetadata, Result, Storage, StoredObject};
+
+/// Objects stored in an s3 compatible bucket
+pub struct S3Storage {
+    bucket: Bucket,
+}
+
+impl S3Storage {
+    pub fn new(bucket: Bucket) -> Self {
+        S3Storage { bucket }
+    }
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    async fn put_stream(&self, mut reader: &mut (dyn AsyncRead + Unpin + Send), key: &str) -> Result<()> {
+        let status = self.bucket.put_object_stream(&mut reader, key).await.context(error::StorageS3Snafu)?;
+        check_status(status)
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        let res = self.bucket.get_object(key).await.context(error::StorageS3Snafu)?;
+        if res.status_code() == 404 {
+            return Err(error::Storage::NotFound { key: key.to_owned() });
+        }
+        Ok(res.bytes().to_vec())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.bucket.delete_object(key).await.context(error::StorageS3Snafu)?;
+        Ok(())
+    }
+
+    async fn copy(&self, from: &str, to: &str) -> Result<()> {
+        // Server side copy, the metadata directive defaults to copying it along
+        let status = self.bucket.copy_object_internal(from, to).await.context(error::StorageS3Snafu)?;
+        if status == 404 {
+            return Err(error::Storage::NotFound { key: from.to_owned() });
+        }
+        check_status(status)
+    }
+
+    async fn presign(&self, key: &str, expiry: Duration) -> Result<String> {
+        self.bucket.presign_get(key, expiry.as_secs() as u32, None).context(error::StorageS3Snafu)
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>> {
+        let pages = self.bucket.list(prefix.to_owned(), None).await.context(error::StorageS3Snafu)?;
+        Ok(pages
+            .into_iter()
+            .flat_map(|page| page.contents)
+            .map(|object| StoredObject {
+                last_modified: DateTime::parse_from_rfc3339(&object.last_modified)
+                    .ok()
+                    .map(|d| d.with_timezone(&Utc)),
+                key: object.key,
+                size: object.size,
+            })
+            .collect())
+    }
+
+    async fn set_metadata(&self, key: &str, metadata: &ObjectMetadata) -> Result<()> {
+        // s3 metadata can't be edited, the object is copied onto itself with the new metadata
+        let mut headers = HeaderMap::new();
+        headers.insert(HeaderName::from_static("x-amz-metadata-directive"), HeaderValue::from_static("REPLACE"));
+        headers.insert(HeaderName::from_static(SIZE_HEADER), HeaderValue::from(metadata.size));
+        let sha256 = HeaderValue::from_str(&metadata.sha256).map_err(|_| error::Storage::Metadata { key: key.to_owned() })?;
+        headers.insert(HeaderName::from_static(SHA256_HEADER), sha256);
+
+        let status = self
+            .bucket
+            .with_extra_headers(headers)
+            .copy_object_internal(key, key)
+            .await
+            .context(error::StorageS3Snafu)?;
+        check_status(status)
+    }
+
+    async fn get_metadata(&self, key: &str) -> Result<Option<ObjectMetadata>> {
+        let (head, status) = self.bucket.head_object(key).await.context(error::StorageS3Snafu)?;
+        if status == 404 {
+            return Err(error::Storage::NotFound { key: key.to_owned() });
+        }
+        // Metadata keys are returned without their header prefix
+        let metadata = head.metadata.unwrap_or_default();
+        let size = metadata.get(SIZE_HEADER.trim_start_matches(META_PREFIX)).and_then(|s| s.parse().ok());
+        let sha256 = metadata.get(SHA256_HEADER.trim_start_matches(META_PREFIX));
+        Ok(match (size, sha256) {
+            (Some(size), Some(sha256)) => Some(ObjectMetadata { size, sha256: sha256.to_owned() }),
+            _ => None,
+        })
+    }
+}
+
+const META_PREFIX: &str = "x-amz-meta-";
+const SIZE_HEADER: &str = "x-amz-meta-size";
+const SHA256_HEADER: &str = "x-amz-meta-sha256";
+
+fn check_status(status: u16) -> Result<()> {
+    if (200..300).contains(&status) {
+        Ok(())
+    } else {
+        Err(error::Storage::UnexpectedStatus { status })
+    }
+}
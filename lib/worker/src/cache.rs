@@ -0,0 +1,275 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use models::{error, Video, VideoURI};
+use sha2::{Digest, Sha256};
+use snafu::ResultExt;
+use tokio::{fs, io::AsyncWriteExt};
+
+#[derive(Debug)]
+struct Entry {
+    path: PathBuf,
+    size: u64,
+    last_used: u64,
+}
+
+/// Local copies of remote inputs, so a video is only downloaded once even if
+/// ffmpeg reads it several times or several jobs are sent for the same attachment
+#[derive(Debug)]
+pub struct InputCache {
+    dir: PathBuf,
+    max_size: u64,
+    budget: u64,
+    /// Content hash of every fetched url
+    urls: HashMap<String, String>,
+    /// Cached files by content hash
+    entries: HashMap<String, Entry>,
+    clock: u64,
+}
+
+impl InputCache {
+    pub async fn new(dir: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+        let dir = std::env::current_dir()?.join(dir);
+        // The index only lives in memory, leftovers from a previous run can't be reused
+        if dir.exists() {
+            fs::remove_dir_all(&dir).await?;
+        }
+        fs::create_dir_all(&dir).await?;
+        Ok(InputCache {
+            dir,
+            max_size: config::get_input_max_size(),
+            budget: config::get_input_cache_budget(),
+            urls: HashMap::new(),
+            entries: HashMap::new(),
+            clock: 0,
+        })
+    }
+
+    /// Returns a copy of `video` pointing to a local file, downloading it if needed.
+    ///
+    /// When `job_dir` is given the cached file is linked into it so eviction
+    /// can't remove an input while a job is still using it
+    pub async fn localize(&mut self, video: &Video, job_dir: Option<&Path>) -> Result<Video, error::Worker> {
+        let VideoURI::Url(url) = &video.url else {
+            return Ok(video.to_owned());
+        };
+        let cached = self.fetch(url, &video.filename).await?;
+
+        let path = match job_dir {
+            Some(dir) => {
+                let dest = dir.join(cached.file_name().unwrap_or_default());
+                // The same input can be used several times in a job (combine)
+                if !dest.exists() && fs::hard_link(&cached, &dest).await.is_err() {
+                    fs::copy(&cached, &dest).await.context(error::IoSnafu)?;
+                }
+                dest
+            }
+            None => cached,
+        };
+        let path = path.to_str().ok_or(error::Worker::Message { msg: "cache path str conversion error".to_owned() })?;
+
+        Ok(Video { url: VideoURI::Path(path.to_owned()), ..video.to_owned() })
+    }
+
+    /// Content hash of an input that was already fetched
+    pub fn get_hash(&self, url: &str) -> Option<&str> {
+        self.urls.get(url).map(|h| h.as_str())
+    }
+
+    async fn fetch(&mut self, url: &str, filename: &str) -> Result<PathBuf, error::Worker> {
+        self.clock += 1;
+        if let Some(entry) = self.urls.get(url).and_then(|hash| self.entries.get_mut(hash)) {
+            if entry.path.exists() {
+                entry.last_used = self.clock;
+                return Ok(entry.path.to_owned());
+            }
+        }
+
+        let file = self.download(url).await?;
+        self.insert(url, filename, file).await
+    }
+
+    async fn download(&self, url: &str) -> Result<PendingFile, error::Worker> {
+        let mut response = reqwest::get(url)
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| error::Input::Download(e.to_string()))
+            .context(error::InputSnafu)?;
+
+        if let Some(length) = response.content_length() {
+            if length > self.max_size {
+                return Err(error::Input::TooLarge(length)).context(error::InputSnafu);
+            }
+        }
+
+        let mut file = PendingFile::create(self.dir.join(format!("download-{}", self.clock)), self.max_size).await?;
+        loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => file.write(&chunk).await?,
+                Ok(None) => break,
+                Err(e) => {
+                    file.discard().await;
+                    return Err(error::Input::Download(e.to_string())).context(error::InputSnafu);
+                }
+            }
+        }
+        Ok(file)
+    }
+
+    /// Moves a received file into the cache, identical contents share one entry
+    async fn insert(&mut self, url: &str, filename: &str, file: PendingFile) -> Result<PathBuf, error::Worker> {
+        let (hash, size, tmp_path) = file.finish().await?;
+        let mut path = self.dir.join(&hash);
+        if let Some(extension) = Path::new(filename).extension() {
+            path.set_extension(extension);
+        }
+        fs::rename(&tmp_path, &path).await.context(error::IoSnafu)?;
+
+        self.urls.insert(url.to_owned(), hash.to_owned());
+        self.entries.insert(hash.to_owned(), Entry { path: path.to_owned(), size, last_used: self.clock });
+        self.evict(&hash).await;
+        Ok(path)
+    }
+
+    /// Removes least recently used entries until the cache fits in its budget
+    async fn evict(&mut self, keep: &str) {
+        let mut total: u64 = self.entries.values().map(|e| e.size).sum();
+        while total > self.budget {
+            let Some(hash) = self.entries
+                .iter()
+                .filter(|(hash, _)| hash.as_str() != keep)
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(hash, _)| hash.to_owned()) else { break };
+            if let Some(entry) = self.entries.remove(&hash) {
+                total -= entry.size;
+                self.urls.retain(|_, h| *h != hash);
+                if let Err(why) = fs::remove_file(&entry.path).await {
+                    println!("Can't evict cached input {:?}: {}", entry.path, why);
+                }
+            }
+        }
+    }
+}
+
+/// A file being received into the cache, hashed and size checked as it is written
+struct PendingFile {
+    path: PathBuf,
+    file: fs::File,
+    hasher: Sha256,
+    size: u64,
+    max_size: u64,
+}
+
+impl PendingFile {
+    async fn create(path: PathBuf, max_size: u64) -> Result<Self, error::Worker> {
+        let file = fs::File::create(&path).await.context(error::IoSnafu)?;
+        Ok(PendingFile { path, file, hasher: Sha256::new(), size: 0, max_size })
+    }
+
+    async fn write(&mut self, chunk: &[u8]) -> Result<(), error::Worker> {
+        self.size += chunk.len() as u64;
+        // Content-Length can be missing or wrong, check what is actually received
+        if self.size > self.max_size {
+            let _ = fs::remove_file(&self.path).await;
+            return Err(error::Input::TooLarge(self.size)).context(error::InputSnafu);
+        }
+        self.hasher.update(chunk);
+        self.file.write_all(chunk).await.context(error::IoSnafu)
+    }
+
+    async fn discard(self) {
+        let _ = fs::remove_file(&self.path).await;
+    }
+
+    /// Content hash, size and path of the complete file
+    async fn finish(mut self) -> Result<(String, u64, PathBuf), error::Worker> {
+        self.file.flush().await.context(error::IoSnafu)?;
+        Ok((format!("{:x}", self.hasher.finalize()), self.size, self.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncRead, AsyncReadExt};
+
+    use super::*;
+
+    async fn temp_cache(name: &str, max_size: u64, budget: u64) -> InputCache {
+        let dir = std::env::temp_dir().join(format!("worker-cache-{}-{}", name, std::process::id()));
+        let mut cache = InputCache::new(dir).await.unwrap();
+        cache.max_size = max_size;
+        cache.budget = budget;
+        cache
+    }
+
+    /// Caches what `reader` sends in small chunks, as a download would
+    async fn receive(cache: &mut InputCache, url: &str, mut reader: impl AsyncRead + Unpin) -> Result<PathBuf, error::Worker> {
+        cache.clock += 1;
+        let mut file = PendingFile::create(cache.dir.join(format!("download-{}", cache.clock)), cache.max_size).await?;
+        let mut buf = [0; 4];
+        loop {
+            match reader.read(&mut buf).await.context(error::IoSnafu)? {
+                0 => break,
+                n => file.write(&buf[..n]).await?,
+            }
+        }
+        cache.insert(url, "video.mp4", file).await
+    }
+
+    fn files(cache: &InputCache) -> usize {
+        std::fs::read_dir(&cache.dir).unwrap().count()
+    }
+
+    #[tokio::test]
+    async fn max_size() {
+        let mut cache = temp_cache("max-size", 10, 100).await;
+        let res = receive(&mut cache, "https://a", b"0123456789abc".as_slice()).await;
+        assert!(matches!(res, Err(error::Worker::Input { source: error::Input::TooLarge(12), .. })));
+        // Nothing is left from the partial download
+        assert_eq!(files(&cache), 0);
+        assert!(cache.get_hash("https://a").is_none());
+
+        let path = receive(&mut cache, "https://a", b"0123456789".as_slice()).await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"0123456789");
+        assert_eq!(path.extension().unwrap(), "mp4");
+        fs::remove_dir_all(&cache.dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn dedup() {
+        let mut cache = temp_cache("dedup", 100, 100).await;
+        let first = receive(&mut cache, "https://a", b"same".as_slice()).await.unwrap();
+        let second = receive(&mut cache, "https://b", b"same".as_slice()).await.unwrap();
+        assert_eq!(first, second);
+        assert_eq!(cache.get_hash("https://a"), cache.get_hash("https://b"));
+        assert_eq!(cache.entries.len(), 1);
+        assert_eq!(files(&cache), 1);
+
+        // A known url is not fetched again
+        assert_eq!(cache.fetch("https://a", "video.mp4").await.unwrap(), first);
+        fs::remove_dir_all(&cache.dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn lru_eviction() {
+        let mut cache = temp_cache("lru", 100, 10).await;
+        let a = receive(&mut cache, "https://a", b"aaaa".as_slice()).await.unwrap();
+        let b = receive(&mut cache, "https://b", b"bbbb".as_slice()).await.unwrap();
+        // Using `a` makes `b` the least recently used
+        cache.fetch("https://a", "video.mp4").await.unwrap();
+        let c = receive(&mut cache, "https://c", b"cccc".as_slice()).await.unwrap();
+
+        assert!(a.exists() && c.exists());
+        assert!(!b.exists());
+        assert!(cache.get_hash("https://b").is_none());
+        assert_eq!(cache.entries.values().map(|e| e.size).sum::<u64>(), 8);
+
+        // The newest entry is kept even when it is over the budget alone
+        let big = receive(&mut cache, "https://d", b"dddddddddddd".as_slice()).await.unwrap();
+        assert!(big.exists());
+        assert_eq!(cache.entries.len(), 1);
+        fs::remove_dir_all(&cache.dir).await.unwrap();
+    }
+}
@@ -1,9 +1,13 @@
+mod cache;
+mod results;
+
 use std::path::Path;
 
+use cache::InputCache;
 use models::{
     error,
     job::{self, Job},
-    StreamKind,
+    CutMode, CutParameters, Overlay, OverlayParameters, StreamKind, Video, VideoContainer, VideoURI,
 };
 use queue::Queue;
 use redis::{Client, Commands};
@@ -15,6 +19,7 @@ enum ProcessError {
     File(std::io::Error),
     Serde(serde_json::Error),
     Redis(redis::RedisError),
+    Storage(error::Storage),
     Error,
 }
 
@@ -36,18 +41,24 @@ impl From<redis::RedisError> for ProcessError {
     }
 }
 
+impl From<error::Storage> for ProcessError {
+    fn from(error: error::Storage) -> Self {
+        ProcessError::Storage(error)
+    }
+}
+
 impl From<error::Interaction> for ProcessError {
     fn from(_: error::Interaction) -> Self {
         ProcessError::Error
     }
 }
 
-async fn process_job(job: Job, client: &mut Client) -> Result<(), ProcessError> {
-    dbg!(&job);
+async fn process_job(job: Job, client: &mut Client, cache: &mut InputCache) -> Result<(), ProcessError> {
     let video = job.video.ok_or(ProcessError::NoVideo)?;
+    let params = job.params;
 
-    match job.kind {
-        models::job::Kind::Parsing => {}
+    let job_dir = match job.kind {
+        models::job::Kind::Parsing => None,
         models::job::Kind::Processing => {
             // Define working directory and destination filepath
             let dir = Path::new("tmpfs").join(format!("{}", &video.id));
@@ -55,30 +66,154 @@ async fn process_job(job: Job, client: &mut Client) -> Result<(), ProcessError>
 
             // Creating working directory
             fs::create_dir(&dir).await?;
+            Some(dir)
+        }
+    };
+
+    let res = run_job(video, params, job_dir.as_deref(), client, cache).await;
+    // Inputs are linked into the job dir, left there they would fill tmpfs outside of the cache budget
+    if let Some(dir) = &job_dir {
+        if let Err(why) = fs::remove_dir_all(dir).await {
+            println!("Can't remove job dir {:?}: {}", dir, why);
         }
     }
+    res
+}
 
+async fn run_job(
+    video: Video,
+    mut params: job::Parameters,
+    job_dir: Option<&Path>,
+    client: &mut Client,
+    cache: &mut InputCache,
+) -> Result<(), ProcessError> {
     let channel = format!("progress:{}", video.id);
 
     let str = serde_json::to_string(&job::Progress::Started)?;
     let _: () = client.publish(&channel, str)?;
 
-    let res = match &job.params {
-        job::Parameters::EncodeToSize(p) => ffedit::encode_to_size(&video, p).await,
-        job::Parameters::Cut(p) => ffedit::cut(&video, p).await,
-        job::Parameters::Remux(p) => ffedit::remux(&video, p).await,
-        job::Parameters::Combine(p) => ffedit::combine(&video, p).await,
-        job::Parameters::Speed(p) => ffedit::speed(&video, p).await,
-        job::Parameters::GetStreams => {
-            if let Ok(res) = ffedit::get_streams(&video).await {
-                let _: () = client.publish(
-                    &channel,
-                    serde_json::to_string(&job::Progress::Response(job::Response::GetStreams(
-                        res,
-                    )))?,
-                )?;
-            };
-            return Ok(());
+    // Results are cached by the original parameters, local paths differ for every job
+    let cache_params = params.clone();
+    let source_url = video.url.to_owned();
+
+    // Download inputs once so ffmpeg doesn't read them from the network on every pass
+    let localized = async {
+        if let job::Parameters::Combine(p) = &mut params {
+            for v in p.videos.iter_mut() {
+                let local = cache.localize(&Video { url: v.url.to_owned(), ..video.to_owned() }, job_dir).await?;
+                v.url = local.url;
+            }
+        }
+        if let job::Parameters::Overlay(OverlayParameters { overlay: Overlay::Image(image) }) = &mut params {
+            image.url = cache.localize(&Video { url: image.url.to_owned(), ..video.to_owned() }, job_dir).await?.url;
+        }
+        if let Some(file) = match &mut params {
+            job::Parameters::Subtitle(p) => p.get_file_mut(),
+            _ => None,
+        } {
+            file.url = cache.localize(&Video { url: file.url.to_owned(), filename: file.filename.to_owned(), ..video.to_owned() }, job_dir).await?.url;
+        }
+        if let job::Parameters::Concat(p) = &mut params {
+            for url in p.videos.iter_mut() {
+                *url = cache.localize(&Video { url: url.to_owned(), ..video.to_owned() }, job_dir).await?.url;
+            }
+        }
+        cache.localize(&video, job_dir).await
+    }.await;
+    let video = match localized {
+        Ok(v) => v,
+        Err(err) => {
+            let _: () = client.publish(
+                &channel,
+                serde_json::to_string(&job::Progress::Error(format!("{}", err)))?,
+            )?;
+            println!("{}", err);
+            return Err(ProcessError::Error);
+        }
+    };
+
+    // Reported before the result, which may come from the cache
+    if let job::Parameters::Cut(p @ CutParameters { mode: CutMode::Keyframe, .. }) = &params {
+        match ffedit::get_cut_bounds(&video, p).await {
+            Ok(bounds) => {
+                let _: () = client.publish(&channel, serde_json::to_string(&job::Progress::Response(job::Response::Cut(bounds)))?)?;
+            }
+            Err(why) => println!("Cut bounds error: {}", why),
+        }
+    }
+
+    let storage = storage::get_storage();
+    // The cache holds a single output per result
+    let result_key = match (&source_url, &job_dir) {
+        (_, Some(_)) if !params.has_single_output() => None,
+        (VideoURI::Url(url), Some(_)) => match cache.get_hash(url) {
+            Some(hash) => {
+                let extension = Path::new(&video.filename).extension().and_then(|e| e.to_str()).unwrap_or_default();
+                Some(results::get_key(hash, extension, &cache_params)?)
+            }
+            None => None,
+        },
+        _ => None,
+    };
+    let cache_hit = match &result_key {
+        Some(key) => results::restore(storage.as_ref(), key, &video.id, config::get_result_cache_ttl()).await.unwrap_or_else(|why| {
+            println!("Result cache error: {:?}", why);
+            false
+        }),
+        None => false,
+    };
+
+    // Operations with several outputs list them, the others store one under the id of the job
+    let mut outputs = Vec::new();
+    let res = if cache_hit {
+        Ok(())
+    } else {
+        match &params {
+            job::Parameters::EncodeToSize(p) => ffedit::encode_to_size(&video, p).await,
+            job::Parameters::Cut(p) => ffedit::cut(&video, p).await,
+            job::Parameters::Remux(p) => ffedit::remux(&video, p).await,
+            job::Parameters::Combine(p) => ffedit::combine(&video, p).await,
+            job::Parameters::Speed(p) => ffedit::speed(&video, p).await,
+            job::Parameters::Transform(p) => ffedit::transform(&video, p).await,
+            job::Parameters::Orient(p) => ffedit::orient(&video, p).await,
+            job::Parameters::Animation(p) => ffedit::animation(&video, p).await,
+            job::Parameters::ExtractAudio(p) => ffedit::extract_audio(&video, p).await,
+            job::Parameters::Loudness(p) => ffedit::loudness(&video, p).await,
+            job::Parameters::Concat(p) => ffedit::concat(&video, p).await,
+            job::Parameters::Overlay(p) => ffedit::overlay(&video, p).await,
+            job::Parameters::Subtitle(p) => ffedit::subtitle(&video, p).await,
+            job::Parameters::Image(p) => ffedit::image(&video, p).await,
+            job::Parameters::Reverse(p) => ffedit::reverse(&video, p).await,
+            job::Parameters::Fade(p) => ffedit::fade(&video, p).await,
+            job::Parameters::Split(p) => ffedit::split(&video, p).await.map(|parts| outputs = parts),
+            job::Parameters::Trim(p) => ffedit::trim(&video, p).await,
+            job::Parameters::DetectDeadAir(p) => {
+                let progress = match ffedit::detect_dead_air(&video, p).await {
+                    Ok(found) => job::Progress::Response(job::Response::DeadAir(found)),
+                    Err(err) => job::Progress::Error(format!("{}", err)),
+                };
+                let _: () = client.publish(&channel, serde_json::to_string(&progress)?)?;
+                return Ok(());
+            }
+            job::Parameters::Scenes(p) => ffedit::scenes(&video, p).await.map(|parts| outputs = parts),
+            job::Parameters::DetectScenes(threshold) => {
+                let progress = match ffedit::detect_scenes(&video, *threshold).await {
+                    Ok(scenes) => job::Progress::Response(job::Response::Scenes(
+                        scenes.into_iter().map(std::time::Duration::from_secs_f64).collect(),
+                    )),
+                    Err(err) => job::Progress::Error(format!("{}", err)),
+                };
+                let _: () = client.publish(&channel, serde_json::to_string(&progress)?)?;
+                return Ok(());
+            }
+            job::Parameters::GetStreams => {
+                let progress = match ffedit::get_streams(&video).await {
+                    Ok(res) => job::Progress::Response(job::Response::GetStreams(res)),
+                    Err(err) => job::Progress::Error(format!("{}", err)),
+                };
+                let _: () = client.publish(&channel, serde_json::to_string(&progress)?)?;
+                return Ok(());
+            }
         }
     };
 
@@ -94,31 +229,71 @@ async fn process_job(job: Job, client: &mut Client) -> Result<(), ProcessError>
         Ok(_) => {}
     }
 
-    let dir = ffedit::get_working_dir(&video.id)?;
-    tokio::fs::remove_dir_all(dir).await?;
+    if let (Some(key), false) = (&result_key, cache_hit) {
+        if let Err(why) = results::store(storage.as_ref(), key, &video.id).await {
+            println!("Result cache error: {:?}", why);
+        }
+    }
+    if let Err(why) = results::evict(storage.as_ref(), config::get_result_cache_ttl()).await {
+        println!("Result cache eviction error: {:?}", why);
+    }
 
-    let file_extension = match job.params {
-        job::Parameters::Remux(container) => container.container.get_file_extension(),
-        job::Parameters::Combine(kind) => {
-            if let StreamKind::Audio = kind.output_kind {
-                "mp3".to_owned()
-            } else {
-                "mp4".to_owned()
+    if outputs.is_empty() {
+        let file_extension = match params {
+            job::Parameters::Remux(container) => container.container.get_file_extension(),
+            job::Parameters::Combine(kind) => {
+                if let StreamKind::Audio = kind.output_kind {
+                    "mp3".to_owned()
+                } else {
+                    "mp4".to_owned()
+                }
             }
-        }
-        _ => Path::new(&video.filename)
-            .extension()
-            .ok_or(ProcessError::Error)?
-            .to_str()
-            .ok_or(ProcessError::Error)?
-            .to_owned(),
-    };
+            job::Parameters::Transform(_) | job::Parameters::Concat(_) | job::Parameters::Overlay(_) | job::Parameters::Reverse(_) | job::Parameters::Speed(_) => "mp4".to_owned(),
+            job::Parameters::Subtitle(p) => p.get_file_extension(),
+            job::Parameters::Image(p) => p.format.get_file_extension(),
+            job::Parameters::Animation(p) => p.format.get_file_extension(),
+            job::Parameters::ExtractAudio(p) => p.container.get_file_extension(),
+            job::Parameters::Cut(p) => p.get_container(video.get_container()).get_file_extension(),
+            job::Parameters::Scenes(p) => p.get_container(video.get_container()).get_file_extension(),
+            job::Parameters::Loudness(_) | job::Parameters::Fade(_) | job::Parameters::Trim(_) => VideoContainer::get_reencode_container(video.get_container()).get_file_extension(),
+            job::Parameters::Orient(p) => match p.get_metadata_container(video.get_container()) {
+                Some(container) => container.get_file_extension(),
+                None => "mp4".to_owned(),
+            },
+            _ => Path::new(&video.filename)
+                .extension()
+                .ok_or(ProcessError::Error)?
+                .to_str()
+                .ok_or(ProcessError::Error)?
+                .to_owned(),
+        };
+
+        let key = storage::output_key(&video.id);
+        let size = get_size(storage.as_ref(), &key).await?;
+        let filename = Path::new(&video.filename).with_extension(file_extension);
+        let filename = filename.to_str().ok_or(ProcessError::Error)?;
+        outputs.push(job::Output::new(key, filename.to_owned(), size));
+    }
 
-    let str = serde_json::to_string(&job::Progress::Done(file_extension.to_owned()))?;
+    let str = serde_json::to_string(&job::Progress::Done { outputs, cache_hit })?;
     let _: () = client.publish(&channel, str)?;
     Ok(())
 }
 
+/// Size of a stored output, results restored from the cache have no metadata
+async fn get_size(storage: &dyn storage::Storage, key: &str) -> Result<u64, ProcessError> {
+    if let Some(metadata) = storage.get_metadata(key).await? {
+        return Ok(metadata.size);
+    }
+    storage
+        .list(key)
+        .await?
+        .into_iter()
+        .find(|o| o.key == key)
+        .map(|o| o.size)
+        .ok_or(ProcessError::Error)
+}
+
 #[tokio::main]
 
 async fn main() {
@@ -132,6 +307,11 @@ async fn main() {
         }
     }
 
+    let mut cache = match InputCache::new("tmpfs/cache").await {
+        Ok(c) => c,
+        Err(why) => panic!("Can't create input cache: {}", why),
+    };
+
     loop {
         let job = Job::receive_job(&mut con).await;
         let job = match job {
@@ -142,7 +322,7 @@ async fn main() {
                 continue;
             }
         };
-        if let Err(why) = process_job(job, &mut client).await {
+        if let Err(why) = process_job(job, &mut client, &mut cache).await {
             println!("Processing error: {:?}", why);
         }
     }
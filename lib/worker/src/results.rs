@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use models::{error, job};
+use sha2::{Digest, Sha256};
+use storage::Storage;
+
+/// Cache key of the result of `params` applied to the input with the content hash `input_hash`.
+///
+/// The output container often follows the input one, which only comes from the extension
+/// of its filename, so identical contents with another `extension` give another key
+pub fn get_key(input_hash: &str, extension: &str, params: &job::Parameters) -> Result<String, serde_json::Error> {
+    let mut hasher = Sha256::new();
+    hasher.update(input_hash.as_bytes());
+    // Separated from the json so `abc` + `.mp4` can't collide with `abc.` + `mp4`
+    hasher.update([0]);
+    hasher.update(extension.to_lowercase().as_bytes());
+    hasher.update([0]);
+    hasher.update(serde_json::to_vec(params)?);
+    Ok(storage::cache_key(&format!("{:x}", hasher.finalize())))
+}
+
+/// Copies a cached result to the output of the job `id`, returns false if there is none
+/// younger than `ttl`
+pub async fn restore(storage: &dyn Storage, key: &str, id: &str, ttl: Duration) -> Result<bool, error::Storage> {
+    let ttl = chrono::Duration::from_std(ttl).unwrap_or(chrono::Duration::max_value());
+    let fresh = storage
+        .list(key)
+        .await?
+        .into_iter()
+        .any(|o| o.key == key && matches!(o.last_modified, Some(d) if Utc::now() - d < ttl));
+    if !fresh {
+        return Ok(false);
+    }
+
+    storage.copy(key, &storage::output_key(id)).await?;
+    Ok(true)
+}
+
+/// Keeps the output of the job `id` to answer identical jobs
+pub async fn store(storage: &dyn Storage, key: &str, id: &str) -> Result<(), error::Storage> {
+    storage.copy(&storage::output_key(id), key).await
+}
+
+/// Deletes the results older than `ttl`
+pub async fn evict(storage: &dyn Storage, ttl: Duration) -> Result<usize, error::Storage> {
+    storage::delete_expired(storage, storage::CACHE_PREFIX, ttl).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use storage::LocalStorage;
+
+    fn temp_storage(name: &str) -> (LocalStorage, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("worker-results-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        (LocalStorage::new(&dir, None), dir)
+    }
+
+    #[test]
+    fn key_stability() {
+        let key = get_key("abc", "mp4", &job::Parameters::DetectScenes(0.4)).unwrap();
+        assert!(key.starts_with(storage::CACHE_PREFIX));
+        assert_eq!(key, get_key("abc", "mp4", &job::Parameters::DetectScenes(0.4)).unwrap());
+        assert_eq!(key, get_key("abc", "MP4", &job::Parameters::DetectScenes(0.4)).unwrap());
+        assert_ne!(key, get_key("abd", "mp4", &job::Parameters::DetectScenes(0.4)).unwrap());
+        assert_ne!(key, get_key("abc", "mp4", &job::Parameters::DetectScenes(0.5)).unwrap());
+        // The same bytes named with another container
+        assert_ne!(key, get_key("abc", "mkv", &job::Parameters::DetectScenes(0.4)).unwrap());
+    }
+
+    #[tokio::test]
+    async fn restore_and_evict() {
+        let (storage, dir) = temp_storage("ttl");
+        let hour = Duration::from_secs(60 * 60);
+        let key = get_key("abc", "mp4", &job::Parameters::GetStreams).unwrap();
+
+        assert!(!restore(&storage, &key, "job1", hour).await.unwrap());
+        storage.put_stream(&mut b"result".as_slice(), &storage::output_key("job1")).await.unwrap();
+        store(&storage, &key, "job1").await.unwrap();
+
+        assert!(restore(&storage, &key, "job2", hour).await.unwrap());
+        assert_eq!(storage.get(&storage::output_key("job2")).await.unwrap(), b"result");
+        // Expired results are neither restored nor kept
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!restore(&storage, &key, "job3", Duration::from_millis(10)).await.unwrap());
+        assert_eq!(evict(&storage, hour).await.unwrap(), 0);
+        assert_eq!(evict(&storage, Duration::from_millis(10)).await.unwrap(), 1);
+        assert!(!restore(&storage, &key, "job3", hour).await.unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,297 +1,626 @@
-use std::path::{Path, PathBuf};
-use std::time::Duration;
-
-use queue::Queue;
-use serenity::async_trait;
-use serenity::builder::CreateApplicationCommand;
-use serenity::futures::StreamExt;
-use serenity::model::prelude::interaction::application_command::ApplicationCommandInteraction;
-use serenity::model::prelude::interaction::message_component::MessageComponentInteraction;
-use serenity::model::prelude::interaction::InteractionResponseType;
-use serenity::model::prelude::Message;
-use serenity::prelude::Context;
-use tokio_stream::Stream;
-
-use crate::flows;
-use models::{error, job, Video};
-
-#[async_trait]
-pub trait EditMessage {
-    async fn edit(
-        &self,
-        http: &serenity::http::Http,
-        message: &str,
-    ) -> Result<(), error::Interaction>;
-}
-
-#[async_trait]
-impl EditMessage for MessageComponentInteraction {
-    async fn edit(
-        &self,
-        http: &serenity::http::Http,
-        message: &str,
-    ) -> Result<(), error::Interaction> {
-        self.edit_original_interaction_response(http.as_ref(), |r| {
-            r.content(message).components(|comp| comp)
-        })
-        .await?;
-        Ok(())
-    }
-}
-
-#[async_trait]
-impl EditMessage for ApplicationCommandInteraction {
-    async fn edit(
-        &self,
-        http: &serenity::http::Http,
-        message: &str,
-    ) -> Result<(), error::Interaction> {
-        self.edit_original_interaction_response(http.as_ref(), |r| {
-            r.content(message).components(|comp| comp)
-        })
-        .await?;
-        Ok(())
-    }
-}
-
-pub trait GetMessage {
-    fn get_message(&self) -> Result<&Message, error::Interaction>;
-}
-
-impl GetMessage for ApplicationCommandInteraction {
-    fn get_message(&self) -> Result<&Message, error::Interaction> {
-        Ok(self
-            .data
-            .resolved
-            .messages
-            .iter()
-            .next()
-            .ok_or(error::Interaction::Error)?
-            .1)
-    }
-}
-
-pub async fn get_streams(video: &Video) -> Result<impl Stream<Item = redis::Msg>, error::Interaction> {
-    let job = job::Job::new(job::Kind::Parsing, Some(video.to_owned()), job::Parameters::GetStreams);
-
-    let client = config::get_redis_client();
-    let mut con = client.get_async_connection().await?;
-    // Send job to redis queue
-    job.send_job(&mut con).await?;
-
-    // Subscribe to status queue
-    let mut pubsub = client.get_async_connection().await?.into_pubsub();
-    let channel = format!("progress:{}", video.id);
-    pubsub.subscribe(&channel).await?;
-    Ok(pubsub.into_on_message())
-}
-
-pub async fn run(
-    cmd: &ApplicationCommandInteraction,
-    ctx: &Context,
-) -> Result<(), error::Interaction> {
-    // Get message the command was called on
-    let message = cmd.get_message()?;
-    let id = cmd.token.to_owned();
-
-    // Check if the message contains a valid number of attachments
-    let number_of_files = message.attachments.len();
-    if number_of_files != 1 {
-        cmd.create_interaction_response(&ctx.http, |response| {
-            response
-                .kind(InteractionResponseType::ChannelMessageWithSource)
-                .interaction_response_data(|message| {
-                    message.content("Le message doit contenir exlusivement une seule vidéo")
-                })
-        })
-        .await?;
-        return Err(error::Interaction::Edit(error::Edit::WrongFileNumber(
-            number_of_files as u32,
-        )));
-    }
-
-    // Create interaction response asking what edit to apply
-    cmd.create_interaction_response(&ctx.http, |response| {
-        response
-            .kind(InteractionResponseType::ChannelMessageWithSource)
-            .interaction_response_data(|m| {
-                m.content(format!(
-                    "Que voulez vous faire avec **{}**...",
-                    message.attachments[0].filename
-                ));
-                m.components(|comps| {
-                    comps.create_action_row(|row| {
-                        row.create_select_menu(|menu| {
-                            menu.custom_id("edit_kind");
-                            menu.placeholder("Choisissez une modification");
-                            menu.options(|f| {
-                                f.create_option(|o| {
-                                    o.label("Changer la taille du fichier (Preview)")
-                                        .value("encode_to_size")
-                                });
-                                f.create_option(|o| {
-                                    o.label("Couper la video (Preview)").value("cut")
-                                });
-                                f.create_option(|o| {
-                                    o.label("Changer le container (Preview)").value("remux")
-                                });
-                                f.create_option(|o| {
-                                    o.label("Combiner des medias (Preview)").value("combine")
-                                });
-                                f.create_option(|o| {
-                                    o.label("Changer la vitesse du média (Preview)").value("speed")
-                                })
-                            })
-                        })
-                    })
-                })
-            })
-    })
-    .await?;
-    // Get message of interaction reponse
-    let interaction_reponse = &cmd.get_interaction_response(&ctx.http).await?;
-
-    // Await edit apply choice (with timeout)
-    let Some(interaction_reponse) = interaction_reponse
-        .await_component_interaction(&ctx)
-        .timeout(Duration::from_secs(60 * 3))
-        .await else {
-        cmd.edit(&ctx.http, "T trop lent, j'ai pas ton temps").await?;
-        return Err(error::Interaction::Timeout);
-    };
-
-    let attachment = &message.attachments[0];
-
-    // Build job obj
-    let video = Video::new(
-        models::VideoURI::Url(attachment.url.to_owned()),
-        Some(id.to_owned()),
-        attachment.filename.to_owned(),
-    );
-
-    // Get edit kind from awaited interaction
-    let edit_kind = &interaction_reponse.data.values[0].to_owned();
-
-    // Match edit kinds and get job parameters
-    let params = match edit_kind.as_str() {
-        "encode_to_size" => flows::encode_to_size::get_info(&cmd, &interaction_reponse, &ctx).await,
-        "cut" => flows::cut::get_info(&cmd, &interaction_reponse, &ctx, &video).await,
-        "remux" => flows::remux::get_info(&cmd, &interaction_reponse, &ctx).await,
-        "combine" => flows::combine::get_info(&cmd, &interaction_reponse, &ctx, &video).await,
-        "speed" => flows::speed::get_info(&cmd, &interaction_reponse, &ctx, &video).await,
-        _ => return Err(error::Interaction::InvalidInput(error::InvalidInput::Error)),
-    }?;
-
-    // Notify file queuing
-    cmd.edit(
-        &ctx.http,
-        &format!(
-            "**{}** à été mit dans la file d'attente",
-            message.attachments[0].filename
-        ),
-    )
-    .await?;
-
-    let client = config::get_redis_client();
-    let mut con = client.get_async_connection().await?;
-
-    let job = job::Job::new(job::Kind::Processing, Some(video), params);
-
-    // Send job to redis queue
-    job.send_job(&mut con).await?;
-
-    // Subscribe to status queue
-    let mut pubsub = client.get_async_connection().await?.into_pubsub();
-    let channel = format!("progress:{}", id);
-    pubsub.subscribe(&channel).await?;
-    let mut msg_stream = pubsub.into_on_message();
-
-    let extension;
-
-    // Wait for done message
-    loop {
-        let payload: String = msg_stream
-            .next()
-            .await
-            .ok_or(error::Interaction::Error)?
-            .get_payload()?;
-        let progress: job::Progress = serde_json::from_str(&payload.as_str())?;
-        match progress {
-            job::Progress::Started => {
-                println!("Starting conversion...");
-                // Notify file queuing
-                cmd.edit(
-                    &ctx.http,
-                    &format!("Modification de **{}**...", message.attachments[0].filename),
-                )
-                .await?;
-            }
-            job::Progress::Done(fe) => {
-                extension = fe;
-                break;
-            }
-            job::Progress::Progress(_) => todo!(),
-            job::Progress::Error(err) => {
-                println!("Erreur du worker: {:?}", err);
-                return Err(error::Interaction::Error);
-            }
-            job::Progress::Response(_) => todo!(),
-        }
-    }
-
-    let bucket = config::get_s3_bucket();
-    let res_files = bucket.get_object(&id).await?;
-    bucket.delete_object(id).await?;
-    let filesize = res_files.bytes().len();
-
-    if filesize > (25 * 2_i32.pow(20)) as usize {
-        cmd.edit(
-            &ctx.http,
-            &format!(
-                "**{}** ne peut pas être envoyé car {:.2}Mo > 25Mo (limite de discord)",
-                message.attachments[0].filename,
-                filesize / 2_usize.pow(20)
-            ),
-        )
-        .await?;
-        return Ok(());
-    }
-
-    // Notify file upload
-    cmd.edit(
-        &ctx.http,
-        &format!(
-            "Envoi de **{}** modifié...",
-            message.attachments[0].filename
-        ),
-    )
-    .await?;
-
-    let mut path = PathBuf::new();
-    path = path.join(Path::new(&attachment.filename));
-    path.set_extension(extension);
-    let filename = path.to_str().ok_or(error::Interaction::Error)?;
-
-    cmd.channel_id
-        .send_message(&ctx.http, |m| {
-            m.content(format!("**{}**:", message.attachments[0].filename));
-            m.files(vec![(res_files.bytes(), filename)])
-        })
-        .await?;
-
-    // Edit original interaction to notify sucess
-    cmd.edit(
-        &ctx.http,
-        &format!(
-            "**{}** à été modifié avec success",
-            message.attachments[0].filename
-        ),
-    )
-    .await?;
-
-    Ok(())
-}
-
-pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
-    command.name("Edit video")
-}
+use std::time::Duration;
+
+use queue::Queue;
+use serenity::async_trait;
+use serenity::builder::CreateApplicationCommand;
+use serenity::futures::StreamExt;
+use serenity::model::prelude::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::prelude::interaction::message_component::MessageComponentInteraction;
+use serenity::model::prelude::interaction::InteractionResponseType;
+use serenity::model::prelude::{Message, PremiumTier};
+use serenity::prelude::Context;
+use tokio_stream::Stream;
+
+use crate::flows;
+use crate::utils::durationparser::DisplayTimestamp;
+use models::{error, job, CutSegment, DeadAir, MediaStream, SceneAction, SceneParameters, TrimParameters, Video};
+
+#[async_trait]
+pub trait EditMessage {
+    async fn edit(
+        &self,
+        http: &serenity::http::Http,
+        message: &str,
+    ) -> Result<(), error::Interaction>;
+}
+
+#[async_trait]
+impl EditMessage for MessageComponentInteraction {
+    async fn edit(
+        &self,
+        http: &serenity::http::Http,
+        message: &str,
+    ) -> Result<(), error::Interaction> {
+        self.edit_original_interaction_response(http.as_ref(), |r| {
+            r.content(message).components(|comp| comp)
+        })
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EditMessage for ApplicationCommandInteraction {
+    async fn edit(
+        &self,
+        http: &serenity::http::Http,
+        message: &str,
+    ) -> Result<(), error::Interaction> {
+        self.edit_original_interaction_response(http.as_ref(), |r| {
+            r.content(message).components(|comp| comp)
+        })
+        .await?;
+        Ok(())
+    }
+}
+
+pub trait GetMessage {
+    fn get_message(&self) -> Result<&Message, error::Interaction>;
+}
+
+impl GetMessage for ApplicationCommandInteraction {
+    fn get_message(&self) -> Result<&Message, error::Interaction> {
+        Ok(self
+            .data
+            .resolved
+            .messages
+            .iter()
+            .next()
+            .ok_or(error::Interaction::Error)?
+            .1)
+    }
+}
+
+/// Max attachment size in the guild the command was used in, depends on its boost tier
+pub async fn get_upload_limit(cmd: &ApplicationCommandInteraction, ctx: &Context) -> u64 {
+    let tier = match cmd.guild_id {
+        Some(guild_id) => guild_id
+            .to_partial_guild(&ctx.http)
+            .await
+            .map(|g| g.premium_tier)
+            .unwrap_or(PremiumTier::Tier0),
+        None => PremiumTier::Tier0,
+    };
+    let limit = match tier {
+        PremiumTier::Tier2 => 50,
+        PremiumTier::Tier3 => 100,
+        _ => 25,
+    };
+    limit * 2_u64.pow(20)
+}
+
+pub async fn get_streams(video: &Video) -> Result<impl Stream<Item = redis::Msg>, error::Interaction> {
+    let job = job::Job::new(job::Kind::Parsing, Some(video.to_owned()), job::Parameters::GetStreams);
+
+    let client = config::get_redis_client();
+    let mut con = client.get_async_connection().await?;
+    // Send job to redis queue
+    job.send_job(&mut con).await?;
+
+    // Subscribe to status queue
+    let mut pubsub = client.get_async_connection().await?.into_pubsub();
+    let channel = format!("progress:{}", video.id);
+    pubsub.subscribe(&channel).await?;
+    Ok(pubsub.into_on_message())
+}
+
+/// Asks the worker for the streams of `video`, showing the analysis in the interaction
+pub async fn fetch_streams(
+    cmd: &ApplicationCommandInteraction,
+    ctx: &Context,
+    video: &Video,
+) -> Result<Vec<MediaStream>, error::Interaction> {
+    let mut msg_stream = get_streams(video).await?;
+    cmd.edit(&ctx.http, &format!("Analyse de **{}**...", video.filename))
+        .await?;
+
+    // Wait for reponse
+    loop {
+        let payload: String = msg_stream
+            .next()
+            .await
+            .ok_or(error::Interaction::Error)?
+            .get_payload()?;
+        let progress: job::Progress = serde_json::from_str(payload.as_str())?;
+        match progress {
+            job::Progress::Error(err) => {
+                println!("Erreur du worker: {:?}", err);
+                return Err(error::Interaction::Error);
+            }
+            job::Progress::Response(job::Response::GetStreams(res)) => return Ok(res),
+            _ => {}
+        }
+    }
+}
+
+/// Sends a parsing job on `video` and returns the response of the worker
+async fn analyse(video: &Video, params: job::Parameters) -> Result<job::Response, error::Interaction> {
+    let client = config::get_redis_client();
+
+    // Subscribe before sending, the job may be done before the subscription otherwise
+    let mut pubsub = client.get_async_connection().await?.into_pubsub();
+    pubsub.subscribe(format!("progress:{}", video.id)).await?;
+    let mut msg_stream = pubsub.into_on_message();
+
+    let job = job::Job::new(job::Kind::Parsing, Some(video.to_owned()), params);
+    job.send_job(&mut client.get_async_connection().await?).await?;
+
+    loop {
+        let payload: String = msg_stream
+            .next()
+            .await
+            .ok_or(error::Interaction::Error)?
+            .get_payload()?;
+        let progress: job::Progress = serde_json::from_str(payload.as_str())?;
+        match progress {
+            job::Progress::Error(err) => {
+                println!("Erreur du worker: {:?}", err);
+                return Err(error::Interaction::Error);
+            }
+            job::Progress::Response(res) => return Ok(res),
+            _ => {}
+        }
+    }
+}
+
+/// Asks the worker for the dead air a trim would remove, showing the analysis in the interaction
+pub async fn detect_dead_air(
+    cmd: &ApplicationCommandInteraction,
+    ctx: &Context,
+    video: &Video,
+    params: &TrimParameters,
+) -> Result<Vec<DeadAir>, error::Interaction> {
+    cmd.edit(&ctx.http, &format!("Recherche des temps morts dans **{}**...", video.filename))
+        .await?;
+    match analyse(video, job::Parameters::DetectDeadAir(params.to_owned())).await? {
+        job::Response::DeadAir(found) => Ok(found),
+        _ => Err(error::Interaction::Error),
+    }
+}
+
+/// Asks the worker for the timestamps the scenes of `video` start at, showing the analysis in the interaction
+pub async fn detect_scenes(
+    cmd: &ApplicationCommandInteraction,
+    ctx: &Context,
+    video: &Video,
+    threshold: f64,
+) -> Result<Vec<Duration>, error::Interaction> {
+    cmd.edit(&ctx.http, &format!("Recherche des scènes de **{}**...", video.filename))
+        .await?;
+    match analyse(video, job::Parameters::DetectScenes(threshold)).await? {
+        job::Response::Scenes(scenes) => Ok(scenes),
+        _ => Err(error::Interaction::Error),
+    }
+}
+
+/// Runs a job on `video` and returns its output, for small outputs shown during a flow
+pub async fn render(video: &Video, params: job::Parameters) -> Result<Vec<u8>, error::Interaction> {
+    let client = config::get_redis_client();
+
+    // Subscribe before sending, the job may be done before the subscription otherwise
+    let mut pubsub = client.get_async_connection().await?.into_pubsub();
+    pubsub.subscribe(format!("progress:{}", video.id)).await?;
+    let mut msg_stream = pubsub.into_on_message();
+
+    let job = job::Job::new(job::Kind::Processing, Some(video.to_owned()), params);
+    job.send_job(&mut client.get_async_connection().await?).await?;
+
+    let output = loop {
+        let payload: String = msg_stream
+            .next()
+            .await
+            .ok_or(error::Interaction::Error)?
+            .get_payload()?;
+        let progress: job::Progress = serde_json::from_str(payload.as_str())?;
+        match progress {
+            job::Progress::Error(err) => {
+                println!("Erreur du worker: {:?}", err);
+                return Err(error::Interaction::Error);
+            }
+            job::Progress::Done { outputs, .. } => break outputs.into_iter().next().ok_or(error::Interaction::Error)?,
+            _ => {}
+        }
+    };
+
+    let storage = storage::get_storage();
+    let data = storage.get(&output.key).await?;
+    storage.delete(&output.key).await?;
+    Ok(data)
+}
+
+pub async fn run(
+    cmd: &ApplicationCommandInteraction,
+    ctx: &Context,
+) -> Result<(), error::Interaction> {
+    // Get message the command was called on
+    let message = cmd.get_message()?;
+    let id = cmd.token.to_owned();
+
+    // Check if the message contains a valid number of attachments
+    let number_of_files = message.attachments.len();
+    if number_of_files != 1 {
+        cmd.create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|message| {
+                    message.content("Le message doit contenir exlusivement une seule vidéo")
+                })
+        })
+        .await?;
+        return Err(error::Interaction::Edit(error::Edit::WrongFileNumber(
+            number_of_files as u32,
+        )));
+    }
+
+    // Create interaction response asking what edit to apply
+    cmd.create_interaction_response(&ctx.http, |response| {
+        response
+            .kind(InteractionResponseType::ChannelMessageWithSource)
+            .interaction_response_data(|m| {
+                m.content(format!(
+                    "Que voulez vous faire avec **{}**...",
+                    message.attachments[0].filename
+                ));
+                m.components(|comps| {
+                    comps.create_action_row(|row| {
+                        row.create_select_menu(|menu| {
+                            menu.custom_id("edit_kind");
+                            menu.placeholder("Choisissez une modification");
+                            menu.options(|f| {
+                                f.create_option(|o| {
+                                    o.label("Changer la taille du fichier (Preview)")
+                                        .value("encode_to_size")
+                                });
+                                f.create_option(|o| {
+                                    o.label("Couper la video (Preview)").value("cut")
+                                });
+                                f.create_option(|o| {
+                                    o.label("Changer le container (Preview)").value("remux")
+                                });
+                                f.create_option(|o| {
+                                    o.label("Combiner des medias (Preview)").value("combine")
+                                });
+                                f.create_option(|o| {
+                                    o.label("Mettre des médias bout à bout (Preview)").value("concat")
+                                });
+                                f.create_option(|o| {
+                                    o.label("Ajouter du texte / une image (Preview)").value("overlay")
+                                });
+                                f.create_option(|o| {
+                                    o.label("Sous-titres (Preview)").value("subtitle")
+                                });
+                                f.create_option(|o| {
+                                    o.label("Extraire une image (Preview)").value("image")
+                                });
+                                f.create_option(|o| {
+                                    o.label("Passer à l'envers / boomerang (Preview)").value("reverse")
+                                });
+                                f.create_option(|o| {
+                                    o.label("Ajouter des fondus (Preview)").value("fade")
+                                });
+                                f.create_option(|o| {
+                                    o.label("Découper en plusieurs parties (Preview)").value("split")
+                                });
+                                f.create_option(|o| {
+                                    o.label("Retirer les temps morts (Preview)").value("trim")
+                                });
+                                f.create_option(|o| {
+                                    o.label("Détecter les scènes (Preview)").value("scenes")
+                                });
+                                f.create_option(|o| {
+                                    o.label("Changer la vitesse du média (Preview)").value("speed")
+                                });
+                                f.create_option(|o| {
+                                    o.label("Recadrer / redimensionner (Preview)").value("transform")
+                                });
+                                f.create_option(|o| {
+                                    o.label("Tourner / retourner (Preview)").value("orient")
+                                });
+                                f.create_option(|o| {
+                                    o.label("Convertir en GIF (Preview)").value("animation")
+                                });
+                                f.create_option(|o| {
+                                    o.label("Extraire l'audio (Preview)").value("extract_audio")
+                                });
+                                f.create_option(|o| {
+                                    o.label("Normaliser le volume (Preview)").value("loudness")
+                                })
+                            })
+                        })
+                    })
+                })
+            })
+    })
+    .await?;
+    // Get message of interaction reponse
+    let interaction_reponse = &cmd.get_interaction_response(&ctx.http).await?;
+
+    // Await edit apply choice (with timeout)
+    let Some(interaction_reponse) = interaction_reponse
+        .await_component_interaction(&ctx)
+        .timeout(Duration::from_secs(60 * 3))
+        .await else {
+        cmd.edit(&ctx.http, "T trop lent, j'ai pas ton temps").await?;
+        return Err(error::Interaction::Timeout);
+    };
+
+    let attachment = &message.attachments[0];
+
+    // Build job obj
+    let video = Video::new(
+        models::VideoURI::Url(attachment.url.to_owned()),
+        Some(id.to_owned()),
+        attachment.filename.to_owned(),
+    );
+
+    // Get edit kind from awaited interaction
+    let edit_kind = &interaction_reponse.data.values[0].to_owned();
+
+    // Match edit kinds and get job parameters
+    let params = match edit_kind.as_str() {
+        "encode_to_size" => flows::encode_to_size::get_info(&cmd, &interaction_reponse, &ctx).await,
+        "cut" => flows::cut::get_info(&cmd, &interaction_reponse, &ctx, &video).await,
+        "remux" => flows::remux::get_info(&cmd, &interaction_reponse, &ctx).await,
+        "combine" => flows::combine::get_info(&cmd, &interaction_reponse, &ctx, &video).await,
+        "speed" => flows::speed::get_info(&cmd, &interaction_reponse, &ctx, &video).await,
+        "transform" => flows::transform::get_info(cmd, &interaction_reponse, ctx).await,
+        "orient" => flows::orient::get_info(cmd, &interaction_reponse, ctx).await,
+        "animation" => flows::animation::get_info(cmd, &interaction_reponse, ctx).await,
+        "extract_audio" => flows::extract_audio::get_info(cmd, &interaction_reponse, ctx, &video).await,
+        "loudness" => flows::loudness::get_info(cmd, &interaction_reponse, ctx).await,
+        "concat" => flows::concat::get_info(cmd, &interaction_reponse, ctx).await,
+        "overlay" => flows::overlay::get_info(cmd, &interaction_reponse, ctx).await,
+        "subtitle" => flows::subtitle::get_info(cmd, &interaction_reponse, ctx, &video).await,
+        "image" => flows::image::get_info(cmd, &interaction_reponse, ctx).await,
+        "reverse" => flows::reverse::get_info(cmd, &interaction_reponse, ctx).await,
+        "fade" => flows::fade::get_info(cmd, &interaction_reponse, ctx).await,
+        "split" => flows::split::get_info(cmd, &interaction_reponse, ctx).await,
+        "trim" => flows::trim::get_info(cmd, &interaction_reponse, ctx, &video).await,
+        "scenes" => flows::scenes::get_info(cmd, &interaction_reponse, ctx).await,
+        _ => return Err(error::Interaction::InvalidInput(error::InvalidInput::Error)),
+    }?;
+
+    // Notify file queuing
+    cmd.edit(
+        &ctx.http,
+        &format!(
+            "**{}** à été mit dans la file d'attente",
+            message.attachments[0].filename
+        ),
+    )
+    .await?;
+
+    let client = config::get_redis_client();
+    let mut con = client.get_async_connection().await?;
+
+    let is_split = matches!(
+        params,
+        job::Parameters::Split(_) | job::Parameters::Scenes(SceneParameters { action: SceneAction::Split, .. })
+    );
+    let job = job::Job::new(job::Kind::Processing, Some(video), params);
+
+    // Subscribe to status queue before sending, a cached result is done right away
+    let mut pubsub = client.get_async_connection().await?.into_pubsub();
+    let channel = format!("progress:{}", id);
+    pubsub.subscribe(&channel).await?;
+    let mut msg_stream = pubsub.into_on_message();
+
+    // Send job to redis queue
+    job.send_job(&mut con).await?;
+
+    let outputs;
+    let cache_hit;
+    let mut cut_bounds = None;
+
+    // Wait for done message
+    loop {
+        let payload: String = msg_stream
+            .next()
+            .await
+            .ok_or(error::Interaction::Error)?
+            .get_payload()?;
+        let progress: job::Progress = serde_json::from_str(&payload.as_str())?;
+        match progress {
+            job::Progress::Started => {
+                println!("Starting conversion...");
+                // Notify file queuing
+                cmd.edit(
+                    &ctx.http,
+                    &format!("Modification de **{}**...", message.attachments[0].filename),
+                )
+                .await?;
+            }
+            job::Progress::Done { outputs: o, cache_hit: hit } => {
+                outputs = o;
+                cache_hit = hit;
+                break;
+            }
+            job::Progress::Progress(_) => {}
+            job::Progress::Error(err) => {
+                println!("Erreur du worker: {:?}", err);
+                return Err(error::Interaction::Error);
+            }
+            job::Progress::Response(job::Response::Cut(bounds)) => cut_bounds = Some(bounds),
+            job::Progress::Response(_) => {}
+        }
+    }
+
+    // Split jobs post one message per part
+    if is_split {
+        return send_parts(cmd, ctx, &message.attachments[0].filename, &outputs).await;
+    }
+
+    let storage = storage::get_storage();
+    let upload_limit = get_upload_limit(cmd, ctx).await;
+    let (too_big, sendable): (Vec<&job::Output>, Vec<&job::Output>) = outputs.iter().partition(|o| o.size > upload_limit);
+
+    // Too big to be sent on discord, give temporary links instead
+    let mut links = Vec::with_capacity(too_big.len());
+    for output in too_big.iter() {
+        links.push(describe_link(storage.as_ref(), output, upload_limit).await?);
+    }
+    if sendable.is_empty() && links.len() == 1 {
+        cmd.edit(&ctx.http, &links[0]).await?;
+        return Ok(());
+    }
+    if !links.is_empty() {
+        cmd.channel_id.send_message(&ctx.http, |m| m.content(links.join("\n"))).await?;
+    }
+
+    let batches = batch_outputs(&sendable, upload_limit);
+    for (i, batch) in batches.iter().enumerate() {
+        // Notify file upload
+        cmd.edit(
+            &ctx.http,
+            &match batches.len() {
+                1 => format!("Envoi de **{}** modifié...", message.attachments[0].filename),
+                count => format!("Envoi de **{}** modifié ({}/{count})...", message.attachments[0].filename, i + 1),
+            },
+        )
+        .await?;
+
+        let mut files = Vec::with_capacity(batch.len());
+        for output in batch.iter() {
+            let data = storage.get(&output.key).await?;
+            // Make sure the whole output was received
+            if data.len() as u64 != output.size {
+                return Err(error::Interaction::Storage(error::Storage::Metadata { key: output.key.to_owned() }));
+            }
+            files.push((data, output.filename.as_str()));
+        }
+
+        cmd.channel_id
+            .send_message(&ctx.http, |m| {
+                m.content(format!("**{}**:", message.attachments[0].filename));
+                m.files(files.iter().map(|(data, filename)| (data.as_slice(), *filename)))
+            })
+            .await?;
+        for output in batch.iter() {
+            storage.delete(&output.key).await?;
+        }
+    }
+
+    // Edit original interaction to notify sucess
+    cmd.edit(
+        &ctx.http,
+        &format!(
+            "**{}** à été modifié avec success{}{}",
+            message.attachments[0].filename,
+            if cache_hit { " (depuis le cache)" } else { "" },
+            match cut_bounds {
+                Some(bounds) => describe_cut_bounds(&bounds)?,
+                None => "".to_owned(),
+            }
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Posts the parts of a split one message each, the ones over the upload limit as temporary links
+async fn send_parts(
+    cmd: &ApplicationCommandInteraction,
+    ctx: &Context,
+    filename: &str,
+    outputs: &[job::Output],
+) -> Result<(), error::Interaction> {
+    let storage = storage::get_storage();
+    let upload_limit = get_upload_limit(cmd, ctx).await;
+    let count = outputs.len();
+    let mut too_big = 0;
+
+    for (i, output) in outputs.iter().enumerate() {
+        cmd.edit(&ctx.http, &format!("Envoi de la partie {}/{count} de **{filename}**...", i + 1)).await?;
+
+        if output.size > upload_limit {
+            too_big += 1;
+            let link = describe_link(storage.as_ref(), output, upload_limit).await?;
+            cmd.channel_id
+                .send_message(&ctx.http, |m| m.content(format!("({}/{count}) {link}", i + 1)))
+                .await?;
+            continue;
+        }
+
+        let data = storage.get(&output.key).await?;
+        // Make sure the whole part was received
+        if data.len() as u64 != output.size {
+            return Err(error::Interaction::Storage(error::Storage::Metadata { key: output.key.to_owned() }));
+        }
+        cmd.channel_id
+            .send_message(&ctx.http, |m| {
+                m.content(format!("**{filename}** ({}/{count}):", i + 1));
+                m.files(vec![(data.as_slice(), output.filename.as_str())])
+            })
+            .await?;
+        storage.delete(&output.key).await?;
+    }
+
+    // Parts limited to the upload size are still over it when keyframes are too far apart
+    cmd.edit(
+        &ctx.http,
+        &match too_big {
+            0 => format!("**{filename}** à été découpé en {count} parties"),
+            n => format!("**{filename}** à été découpé en {count} parties, {n} dépassent la limite de discord et sont envoyées en lien"),
+        },
+    )
+    .await?;
+    Ok(())
+}
+
+/// Temporary link to an output too big to be sent on discord
+async fn describe_link(storage: &dyn storage::Storage, output: &job::Output, upload_limit: u64) -> Result<String, error::Interaction> {
+    let expiry = config::get_presign_expiry();
+    let url = storage.presign(&output.key, expiry).await?;
+    // Links that don't expire last until the hourly cleanup deletes the output
+    let availability = if storage.presign_expires() {
+        format!("il est disponible pendant {}h ici", expiry.as_secs() / 3600)
+    } else {
+        format!("il est disponible ici jusqu'à sa suppression dans environ {}h", expiry.as_secs() / 3600 + 1)
+    };
+    Ok(format!(
+        "**{}** est trop lourd pour discord ({:.2}Mo > {}Mo), {}: {}",
+        output.filename,
+        output.size as f64 / 2_f64.powf(20.0),
+        upload_limit / 2_u64.pow(20),
+        availability,
+        url
+    ))
+}
+
+/// Attachments a discord message can hold
+const MAX_ATTACHMENTS: usize = 10;
+
+/// Groups outputs into messages, the files of a message stay under the upload limit together
+fn batch_outputs<'a>(outputs: &[&'a job::Output], upload_limit: u64) -> Vec<Vec<&'a job::Output>> {
+    let mut batches: Vec<Vec<&job::Output>> = Vec::new();
+    let mut size = 0;
+    for output in outputs {
+        match batches.last_mut() {
+            Some(batch) if batch.len() < MAX_ATTACHMENTS && size + output.size <= upload_limit => {
+                batch.push(output);
+                size += output.size;
+            }
+            _ => {
+                batches.push(vec![output]);
+                size = output.size;
+            }
+        }
+    }
+    batches
+}
+
+/// Ranges a keyframe cut actually kept, they can start before the requested timestamps
+fn describe_cut_bounds(bounds: &[CutSegment]) -> Result<String, error::Interaction> {
+    let mut ranges = Vec::with_capacity(bounds.len());
+    for segment in bounds {
+        let start = chrono::Duration::from_std(segment.start.unwrap_or_default())?;
+        let end = chrono::Duration::from_std(segment.end.unwrap_or_default())?;
+        ranges.push(format!("{} → {}", start.display_timestamp()?, end.display_timestamp()?));
+    }
+    Ok(format!("\nCoupé aux images clés: {}", ranges.join(", ")))
+}
+
+pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command.name("Edit video")
+}
@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+use serenity::{
+    model::prelude::interaction::{
+        application_command::ApplicationCommandInteraction,
+        message_component::MessageComponentInteraction,
+    },
+    prelude::Context,
+};
+
+use models::{error, job, AnimationFormat, AnimationParameters, Dither};
+
+use crate::{
+    commands::edit::{EditMessage, GetMessage},
+    utils::modal::{ask, parse_optional},
+};
+
+const FORMATS: [(&str, &str, AnimationFormat, Dither); 4] = [
+    ("GIF", "gif", AnimationFormat::Gif, Dither::Sierra2_4a),
+    ("GIF sans tramage (dessins, captures d'écran)", "gif_flat", AnimationFormat::Gif, Dither::None),
+    ("WebP animé", "webp", AnimationFormat::WebP, Dither::None),
+    ("APNG", "apng", AnimationFormat::APng, Dither::None),
+];
+
+pub async fn get_info(
+    cmd: &ApplicationCommandInteraction,
+    interaction_reponse: &MessageComponentInteraction,
+    ctx: &Context,
+) -> Result<job::Parameters, error::Interaction> {
+    interaction_reponse.defer(&ctx.http).await?;
+
+    let sender_message = cmd.get_message()?;
+
+    cmd.edit_original_interaction_response(&ctx.http, |m| {
+        m.content(format!(
+            "En quel format animer **{}** ?",
+            sender_message.attachments[0].filename
+        ));
+        m.components(|comps| {
+            comps.create_action_row(|row| {
+                row.create_select_menu(|menu| {
+                    menu.custom_id("animation_format");
+                    menu.placeholder("Choisissez un format");
+                    menu.options(|f| {
+                        for (label, value, _, _) in FORMATS {
+                            f.create_option(|o| o.label(label).value(value));
+                        }
+                        f
+                    })
+                })
+            })
+        })
+    })
+    .await?;
+
+    // Await format choice (with timeout)
+    let interaction_reponse = &cmd.get_interaction_response(&ctx.http).await?;
+    let Some(interaction) = interaction_reponse
+        .await_component_interaction(ctx)
+        .timeout(Duration::from_secs(60 * 3))
+        .await else {
+        cmd.edit(&ctx.http, "T trop lent, j'ai pas ton temps").await?;
+        return Err(error::Interaction::Timeout);
+    };
+
+    let (_, _, format, dither) = FORMATS
+        .into_iter()
+        .find(|(_, value, _, _)| *value == interaction.data.values[0])
+        .ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error))?;
+
+    let values = ask(
+        cmd,
+        &interaction,
+        ctx,
+        "Options de l'animation",
+        &[
+            ("Images par seconde", "Par défaut: 15"),
+            ("Largeur", "Par défaut: 480"),
+            ("Nombre de lectures", "Par défaut: 0 (en boucle)"),
+            ("Taille max (Mo)", "Ex: 8"),
+        ],
+    )
+    .await?;
+
+    let target_size = parse_optional::<f64>(&values[3].replace(',', "."))?
+        .map(|mb| (mb * 2_f64.powf(20.0)) as u64);
+
+    Ok(job::Parameters::Animation(AnimationParameters {
+        format,
+        fps: parse_optional(&values[0])?.unwrap_or(15),
+        width: Some(parse_optional(&values[1])?.unwrap_or(480)),
+        loops: parse_optional(&values[2])?.unwrap_or(0),
+        dither,
+        target_size,
+    }))
+}
@@ -14,7 +14,7 @@ use serenity::{
     prelude::Context,
 };
 
-use models::{error, job, CombineParameters, CombineVideo, MediaStream, StreamKind, Video};
+use models::{error, job, CombineParameters, CombineVideo, MediaStream, StreamKind, Video, VideoURI};
 
 use crate::commands::edit::{EditMessage, GetMessage};
 
@@ -48,6 +48,7 @@ async fn get_streams(
             }
             job::Progress::Response(res) => match res {
                 job::Response::GetStreams(res) => return Ok(res),
+                job::Response::Cut(_) | job::Response::DeadAir(_) | job::Response::Scenes(_) => {}
             },
             _ => {}
         }
@@ -60,12 +61,8 @@ async fn update_msg(
     ctx: &Context,
     streams: &Vec<StreamState>,
 ) -> Result<(), error::Interaction> {
-    fn get_name(stream: &MediaStream) -> &str {
-        match stream.kind {
-            models::StreamKind::Video => "Video",
-            models::StreamKind::Audio => "Audio",
-            models::StreamKind::Unknown => "toz",
-        }
+    fn get_name(stream: &MediaStream) -> String {
+        stream.describe()
     }
     cmd.edit_original_interaction_response(&ctx.http, |m| {
         let mut streams_str = String::new();
@@ -225,12 +222,13 @@ pub async fn get_info(
                     (Some(StreamKind::Video), StreamKind::Audio) => Some(StreamKind::Video),
                     (Some(StreamKind::Audio), StreamKind::Video) => Some(StreamKind::Video),
                     (Some(StreamKind::Audio), StreamKind::Audio) => Some(StreamKind::Audio),
-                    _ => None,
+                    // Subtitles, data and attachments don't change the output kind
+                    (k, _) => k.to_owned(),
                 };
                 hashmap.insert(
                     x.url.to_owned(),
                     CombineVideo {
-                        url: x.url.to_owned(),
+                        url: VideoURI::Url(x.url.to_owned()),
                         selected_streams: Vec::new(),
                     },
                 );
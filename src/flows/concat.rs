@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use serenity::{
+    model::prelude::interaction::{
+        application_command::ApplicationCommandInteraction,
+        message_component::MessageComponentInteraction,
+    },
+    prelude::Context,
+};
+
+use models::{error, job, ConcatParameters, Transition, TransitionKind, VideoURI};
+
+use crate::{
+    commands::edit::{EditMessage, GetMessage},
+    utils::select::choose,
+};
+
+/// Transition lengths offered, in milliseconds
+const TRANSITION_LENGTHS: [u64; 3] = [500, 1000, 2000];
+
+struct Clip {
+    filename: String,
+    url: String,
+}
+
+async fn update_msg(
+    cmd: &ApplicationCommandInteraction,
+    ctx: &Context,
+    clips: &[Clip],
+) -> Result<(), error::Interaction> {
+    cmd.edit_original_interaction_response(&ctx.http, |m| {
+        let mut content = String::from("Médias mis bout à bout, dans l'ordre :\n");
+        for (i, clip) in clips.iter().enumerate() {
+            content.push_str(&format!("{}. **{}**\n", i + 1, clip.filename));
+        }
+        content.push_str("\nRépondez à ce message avec d'autres médias pour les ajouter à la suite.");
+        m.content(content);
+        m.components(|comps| {
+            comps.create_action_row(|r| {
+                r.create_button(|b| {
+                    b.custom_id("remove_last");
+                    b.label("Retirer le dernier");
+                    b.disabled(clips.len() < 2)
+                });
+                r.create_button(|b| {
+                    b.custom_id("confirm");
+                    b.label("Valider");
+                    b.disabled(clips.len() < 2)
+                })
+            })
+        })
+    })
+    .await?;
+    Ok(())
+}
+
+pub async fn get_info(
+    cmd: &ApplicationCommandInteraction,
+    interaction_reponse: &MessageComponentInteraction,
+    ctx: &Context,
+) -> Result<job::Parameters, error::Interaction> {
+    interaction_reponse.defer(&ctx.http).await?;
+
+    let sender_message = cmd.get_message()?;
+    let mut clips = vec![Clip {
+        filename: sender_message.attachments[0].filename.to_owned(),
+        url: sender_message.attachments[0].url.to_owned(),
+    }];
+
+    loop {
+        update_msg(cmd, ctx, &clips).await?;
+
+        // Await confirmation or new clips (with timeout)
+        let interaction_reponse = &cmd.get_interaction_response(&ctx.http).await?;
+        let interaction_id = cmd.id;
+
+        tokio::select! {
+            i = interaction_reponse.await_component_interaction(ctx).timeout(Duration::from_secs(60 * 3)) => {
+                let Some(interaction) = i else {
+                    cmd.edit(&ctx.http, "T trop lent, j'ai pas ton temps").await?;
+                    return Err(error::Interaction::Timeout);
+                };
+                interaction.defer(&ctx.http).await?;
+                match interaction.data.custom_id.as_str() {
+                    "confirm" => break,
+                    "remove_last" if clips.len() > 1 => {
+                        clips.pop();
+                    }
+                    _ => {}
+                }
+            },
+            msg = cmd.user.await_reply(ctx).filter(move |x| {
+                matches!(x.referenced_message.as_ref().and_then(|m| m.interaction.as_ref()), Some(i) if i.id == interaction_id)
+            }) => {
+                let Some(msg) = msg else { continue };
+                clips.extend(msg.attachments.iter().map(|a| Clip {
+                    filename: a.filename.to_owned(),
+                    url: a.url.to_owned(),
+                }));
+            }
+        };
+    }
+
+    Ok(job::Parameters::Concat(ConcatParameters {
+        videos: clips.into_iter().map(|c| VideoURI::Url(c.url)).collect(),
+        transition: ask_transition(cmd, ctx).await?,
+    }))
+}
+
+async fn ask_transition(cmd: &ApplicationCommandInteraction, ctx: &Context) -> Result<Option<Transition>, error::Interaction> {
+    let mut options = vec![("Aucune".to_owned(), "none".to_owned())];
+    options.extend(TransitionKind::ALL.iter().enumerate().map(|(i, k)| (k.get_name().to_owned(), i.to_string())));
+    let choice = choose(cmd, ctx, "Quelle transition entre les médias ?", "Choisissez une transition", &options).await?;
+    if choice == "none" {
+        return Ok(None);
+    }
+    let kind = *TransitionKind::ALL
+        .get(choice.parse::<usize>()?)
+        .ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error))?;
+
+    let lengths: Vec<_> = TRANSITION_LENGTHS
+        .iter()
+        .map(|ms| (format!("{} s", *ms as f64 / 1000.0), ms.to_string()))
+        .collect();
+    let length: u64 = choose(cmd, ctx, "Quelle durée pour la transition ?", "Choisissez une durée", &lengths).await?.parse()?;
+
+    Ok(Some(Transition {
+        kind,
+        duration: Duration::from_millis(length),
+    }))
+}
@@ -8,10 +8,16 @@ use serenity::{
     prelude::Context,
 };
 
-use models::{CutParameters, job, error, Video};
+use models::{CutMode, CutParameters, CutSegment, job, error, ImageFormat, ImageKind, ImageParameters, SceneParameters, Video};
 use tokio_stream::StreamExt;
 
-use crate::{commands::edit::EditMessage, utils::{durationparser::DisplayTimestamp, self}};
+use crate::{commands::edit::{detect_scenes, render, EditMessage}, utils::{durationparser::DisplayTimestamp, select::choose, self}};
+
+/// Scenes offered as quick bounds, the select menu keeps one option for the modal
+const SCENE_MAX_CHOICES: usize = 24;
+
+/// Start, end and the other segments to cut
+type Bounds = (chrono::Duration, chrono::Duration, Vec<(chrono::Duration, chrono::Duration)>);
 
 pub async fn get_info(
     cmd: &ApplicationCommandInteraction,
@@ -50,17 +56,143 @@ pub async fn get_info(
                     let stream = res.first().ok_or(error::Interaction::Error)?;
                     break stream.duration;
                 }
+                job::Response::Cut(_) | job::Response::DeadAir(_) | job::Response::Scenes(_) => {}
             },
-            job::Progress::Progress(_) => todo!(),
-            job::Progress::Done(_) => todo!(),
+            job::Progress::Progress(_) | job::Progress::Done { .. } => {}
         }
     };
     let duration = chrono::Duration::from_std(std::time::Duration::from_micros(micros as u64))?;
     let display_timestamp = duration.display_timestamp()?;
     let zero_timestamp = chrono::Duration::zero().display_timestamp()?;
 
+    // Scene detection decodes the whole video, it only runs when asked for
+    interaction_reponse.defer(&ctx.http).await?;
+    let manual = ("Choisir à la main...".to_owned(), "manual".to_owned());
+    let options = [manual.to_owned(), ("Proposer les scènes détectées...".to_owned(), "scenes".to_owned())];
+    let interaction = pick(cmd, ctx, &format!("Comment choisir la partie de **{}** à couper ?", video.filename), "Choisissez une méthode", &options).await?;
+    // Bounds of the chosen scene, or the interaction opening the modal
+    let chosen = match interaction.data.values.first().map(|v| v.as_str()) {
+        Some("scenes") => {
+            interaction.defer(&ctx.http).await?;
+            let scenes = detect_scenes(cmd, ctx, video, SceneParameters::DEFAULT_THRESHOLD).await?;
+            if scenes.is_empty() {
+                cmd.edit(&ctx.http, &format!("Aucune scène trouvée dans **{}**", video.filename)).await?;
+                return Err(error::Interaction::InvalidInput(error::InvalidInput::Error));
+            }
+            let bounds: Vec<_> = std::iter::once(Duration::ZERO)
+                .chain(scenes)
+                .chain(std::iter::once(duration.to_std()?))
+                .collect();
+            let scenes: Vec<_> = bounds.windows(2).map(|w| (w[0], w[1])).take(SCENE_MAX_CHOICES).collect();
+
+            let mut options = vec![manual];
+            options.extend(scenes.iter().enumerate().map(|(i, (s, e))| {
+                (format!("Scène {}: {} → {}", i + 1, timestamp(*s), timestamp(*e)), i.to_string())
+            }));
+            let interaction = pick(cmd, ctx, &format!("Quelle partie de **{}** couper ?", video.filename), "Choisissez une scène", &options).await?;
+            let scene = interaction
+                .data
+                .values
+                .first()
+                .and_then(|v| v.parse::<usize>().ok())
+                .and_then(|i| scenes.get(i));
+            if let Some((s, e)) = scene {
+                interaction.defer(&ctx.http).await?;
+                Ok((chrono::Duration::from_std(*s)?, chrono::Duration::from_std(*e)?, Vec::new()))
+            } else {
+                Err(interaction)
+            }
+        }
+        _ => Err(interaction),
+    };
+    let (start, end, others) = match chosen {
+        Ok(bounds) => bounds,
+        Err(interaction) => ask_bounds(cmd, &interaction, ctx, zero_timestamp, display_timestamp).await?,
+    };
+
+    match (start, end) {
+        (s, e) if (s, e) < (chrono::Duration::zero(), chrono::Duration::zero()) => cmd.edit(&ctx.http, "Les nombres ne peuvent pas être négatives").await?,
+        (s, e) if s.is_zero() && e.is_zero() => cmd.edit(&ctx.http, "Les deux nombres de peuvent pas valoir 0").await?,
+        (s, e) if s > e || others.iter().any(|(s, e)| s >= e) => cmd.edit(&ctx.http, "Le debut de la vidéo doit être avant la fin").await?,
+        (s, e) => {
+            let remove = choose(
+                cmd,
+                ctx,
+                &format!("Que faire des segments choisis dans **{}** ?", video.filename),
+                "Choisissez une action",
+                &[("Les garder".to_owned(), "keep".to_owned()), ("Les retirer".to_owned(), "remove".to_owned())],
+            )
+            .await? == "remove";
+
+            let modes: Vec<_> = CutMode::ALL.iter().enumerate().map(|(i, m)| (m.get_name().to_owned(), i.to_string())).collect();
+            let mode: usize = choose(cmd, ctx, "Comment couper ?", "Choisissez une méthode", &modes).await?.parse()?;
+            let mode = *CutMode::ALL.get(mode).ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error))?;
+
+            // The frames only match the bounds of a single kept segment
+            let previewed = others.is_empty() && !remove;
+            if !previewed || preview(cmd, ctx, video, s, e).await? {
+                let mut segments = vec![CutSegment { start: Some(s.to_std()?), end: Some(e.to_std()?) }];
+                for (s, e) in others {
+                    segments.push(CutSegment { start: Some(s.to_std()?), end: Some(e.to_std()?) });
+                }
+                return Ok(job::Parameters::Cut(CutParameters { segments, remove, mode }));
+            }
+            cmd.edit(&ctx.http, "Coupe annulée").await?
+        }
+    }
+    Err(error::Interaction::InvalidInput(error::InvalidInput::Error))
+}
+
+/// Replaces the interaction message with a select menu, returning the interaction
+/// of the choice so it can still open a modal
+async fn pick(
+    cmd: &ApplicationCommandInteraction,
+    ctx: &Context,
+    content: &str,
+    placeholder: &str,
+    options: &[(String, String)],
+) -> Result<MessageComponentInteraction, error::Interaction> {
+    cmd.edit_original_interaction_response(&ctx.http, |m| {
+        m.content(content);
+        m.components(|comps| {
+            comps.create_action_row(|row| {
+                row.create_select_menu(|menu| {
+                    menu.custom_id("bounds");
+                    menu.placeholder(placeholder);
+                    menu.options(|f| {
+                        for (label, value) in options {
+                            f.create_option(|o| o.label(label).value(value));
+                        }
+                        f
+                    })
+                })
+            })
+        })
+    })
+    .await?;
+
+    // Await choice (with timeout)
+    let interaction_reponse = &cmd.get_interaction_response(&ctx.http).await?;
+    let Some(interaction) = interaction_reponse
+        .await_component_interaction(ctx)
+        .timeout(Duration::from_secs(60 * 3))
+        .await else {
+        cmd.edit(&ctx.http, "T trop lent, j'ai pas ton temps").await?;
+        return Err(error::Interaction::Timeout);
+    };
+    Ok(interaction.as_ref().to_owned())
+}
+
+/// Asks for the start, end and other segments to cut in a modal
+async fn ask_bounds(
+    cmd: &ApplicationCommandInteraction,
+    interaction: &MessageComponentInteraction,
+    ctx: &Context,
+    zero_timestamp: String,
+    display_timestamp: String,
+) -> Result<Bounds, error::Interaction> {
     // Display modal asking for target size
-    interaction_reponse.create_interaction_response(&ctx.http, |response| {
+    interaction.create_interaction_response(&ctx.http, |response| {
         response
             .kind(InteractionResponseType::Modal)
             .interaction_response_data(|modal| {
@@ -86,13 +218,23 @@ pub async fn get_info(
                                 menu.label("Fin");
                                 menu.max_length(10)
                             })
+                        });
+                        comp.create_action_row(|r| {
+                            r.create_input_text(|menu| {
+                                menu.custom_id("segments");
+                                menu.placeholder("01:00-01:30; 02:00-02:10");
+                                menu.style(InputTextStyle::Paragraph);
+                                menu.label("Autres segments (début-fin)");
+                                menu.required(false);
+                                menu.max_length(300)
+                            })
                         })
                     })
             })
     })
     .await?;
     // Get message of interaction reponse
-    let interaction_reponse = &interaction_reponse.get_interaction_response(&ctx.http).await?;
+    let interaction_reponse = &interaction.get_interaction_response(&ctx.http).await?;
 
     // Await modal reponse
     let interaction = match interaction_reponse
@@ -118,14 +260,98 @@ pub async fn get_info(
         _ => return Err(error::Interaction::Error),
     };
 
+    let others: &ActionRowComponent = &interaction.data.components[2].components[0];
+    let others = match others {
+        ActionRowComponent::InputText(txt) => parse_segments(&txt.value)?,
+        _ => return Err(error::Interaction::Error),
+    };
+
     // Ack modal interaction
     interaction.defer(&ctx.http).await?;
+    Ok((start, end, others))
+}
 
-    match (start, end) {
-        (s, e) if (s, e) < (chrono::Duration::zero(), chrono::Duration::zero()) => cmd.edit(&ctx.http, "Les nombres ne peuvent pas être négatives").await?,
-        (s, e) if s.is_zero() && e.is_zero() => cmd.edit(&ctx.http, "Les deux nombres de peuvent pas valoir 0").await?,
-        (s, e) if s > e => cmd.edit(&ctx.http, "Le debut de la vidéo doit être avant la fin").await?,
-        (s, e) => return Ok(job::Parameters::Cut(CutParameters {start: Some(s.to_std()?), end: Some(e.to_std()?) }))
+fn timestamp(d: Duration) -> String {
+    chrono::Duration::from_std(d)
+        .ok()
+        .and_then(|d| d.display_timestamp().ok())
+        .unwrap_or_default()
+}
+
+/// Segments written as `début-fin`, separated by new lines or `;`
+fn parse_segments(text: &str) -> Result<Vec<(chrono::Duration, chrono::Duration)>, error::Interaction> {
+    let mut segments = Vec::new();
+    for segment in text.split(['\n', ';']).map(str::trim).filter(|s| !s.is_empty()) {
+        let (start, end) = segment
+            .split_once('-')
+            .ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error))?;
+        segments.push((
+            utils::durationparser::parse(&start.trim().to_owned())?,
+            utils::durationparser::parse(&end.trim().to_owned())?,
+        ));
     }
-    Err(error::Interaction::InvalidInput(error::InvalidInput::Error))
+    Ok(segments)
+}
+
+/// Shows the frames at the chosen start and end, returns whether the user confirmed them
+async fn preview(
+    cmd: &ApplicationCommandInteraction,
+    ctx: &Context,
+    video: &Video,
+    start: chrono::Duration,
+    end: chrono::Duration,
+) -> Result<bool, error::Interaction> {
+    cmd.edit(&ctx.http, "Génération de l'aperçu...").await?;
+
+    let frame = |suffix: &str, timestamp: Duration| {
+        let video = Video { id: format!("{}-{}", video.id, suffix), ..video.to_owned() };
+        async move {
+            let params = ImageParameters { kind: ImageKind::Frame(timestamp), format: ImageFormat::Jpeg };
+            render(&video, job::Parameters::Image(params)).await
+        }
+    };
+    // The end itself is past the last frame
+    let last = end.to_std()?.saturating_sub(Duration::from_millis(100));
+    let frames = tokio::try_join!(frame("start", start.to_std()?), frame("end", last));
+    // The preview is only a help, the cut can go on without it
+    let (first, last) = match frames {
+        Ok(frames) => frames,
+        Err(why) => {
+            println!("Aperçu impossible: {:?}", why);
+            return Ok(true);
+        }
+    };
+
+    let followup = cmd
+        .create_followup_message(&ctx.http, |f| {
+            f.content(format!("Début ({}) et fin ({}):", start.display_timestamp().unwrap_or_default(), end.display_timestamp().unwrap_or_default()))
+                .add_files(vec![(first.as_slice(), "debut.jpg"), (last.as_slice(), "fin.jpg")])
+        })
+        .await?;
+    cmd.edit_original_interaction_response(&ctx.http, |m| {
+        m.content(format!("Couper **{}** ici ?", video.filename));
+        m.components(|comps| {
+            comps.create_action_row(|r| {
+                r.create_button(|b| b.custom_id("confirm").label("Valider"));
+                r.create_button(|b| b.custom_id("cancel").label("Annuler"))
+            })
+        })
+    })
+    .await?;
+
+    // Await confirmation (with timeout)
+    let interaction_reponse = &cmd.get_interaction_response(&ctx.http).await?;
+    let interaction = interaction_reponse
+        .await_component_interaction(ctx)
+        .timeout(Duration::from_secs(60 * 3))
+        .await;
+    if let Err(why) = cmd.delete_followup_message(&ctx.http, followup.id).await {
+        println!("Suppression de l'aperçu impossible: {:?}", why);
+    }
+    let Some(interaction) = interaction else {
+        cmd.edit(&ctx.http, "T trop lent, j'ai pas ton temps").await?;
+        return Err(error::Interaction::Timeout);
+    };
+    interaction.defer(&ctx.http).await?;
+    Ok(interaction.data.custom_id == "confirm")
 }
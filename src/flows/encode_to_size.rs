@@ -10,7 +10,7 @@ use serenity::{
 
 use models::{job, EncodeToSizeParameters, error};
 
-use crate::commands::edit::{EditMessage, GetMessage};
+use crate::commands::edit::{get_upload_limit, EditMessage, GetMessage};
 
 pub async fn get_info(
     cmd: &ApplicationCommandInteraction,
@@ -75,8 +75,9 @@ pub async fn get_info(
             Err(error::Interaction::InvalidInput(error::InvalidInput::StringParse(err)))
         },
         Ok(t) => {
-            if t > 45.0 {
-                cmd.edit(&ctx.http, &format!("**{}** ne pourra pas être envoyé car {}Mo > 25Mo (limite de discord)", message.attachments[0].filename, t)).await?;
+            let upload_limit = get_upload_limit(cmd, ctx).await as f32 / 2_f32.powf(20.0);
+            if t > upload_limit {
+                cmd.edit(&ctx.http, &format!("**{}** ne pourra pas être envoyé car {}Mo > {}Mo (limite de discord)", message.attachments[0].filename, t, upload_limit)).await?;
                 Err(error::Interaction::InvalidInput(error::InvalidInput::Error))
             } else {
                 Ok(job::Parameters::EncodeToSize(EncodeToSizeParameters {
@@ -0,0 +1,113 @@
+use serenity::{
+    model::prelude::interaction::{
+        application_command::ApplicationCommandInteraction,
+        message_component::MessageComponentInteraction,
+    },
+    prelude::Context,
+};
+
+use models::{error, job, ExtractAudioParameters, StreamKind, Video, VideoContainer};
+
+use crate::{
+    commands::edit::{fetch_streams, EditMessage},
+    utils::select::choose,
+};
+
+/// Containers offered for transcoding
+const FORMATS: [(&str, VideoContainer); 4] = [
+    ("MP3", VideoContainer::MP3),
+    ("Opus", VideoContainer::OPUS),
+    ("FLAC (sans perte)", VideoContainer::FLAC),
+    ("WAV (sans perte)", VideoContainer::WAV),
+];
+
+const BIT_RATES: [u32; 5] = [96, 128, 192, 256, 320];
+
+pub async fn get_info(
+    cmd: &ApplicationCommandInteraction,
+    interaction_reponse: &MessageComponentInteraction,
+    ctx: &Context,
+    video: &Video,
+) -> Result<job::Parameters, error::Interaction> {
+    interaction_reponse.defer(&ctx.http).await?;
+
+    let streams: Vec<_> = fetch_streams(cmd, ctx, video)
+        .await?
+        .into_iter()
+        .filter(|s| s.kind == StreamKind::Audio)
+        .collect();
+
+    // Only ask which track when there is a choice
+    let stream = match &streams[..] {
+        [] => {
+            cmd.edit(&ctx.http, &format!("**{}** n'a pas de piste audio", video.filename))
+                .await?;
+            return Err(error::Interaction::InvalidInput(error::InvalidInput::Error));
+        }
+        [stream] => stream,
+        _ => {
+            let options: Vec<_> = streams
+                .iter()
+                .map(|s| (format!("Piste {}: {}", s.id, s.describe()), s.id.to_string()))
+                .collect();
+            let id: usize = choose(
+                cmd,
+                ctx,
+                &format!("Quelle piste extraire de **{}** ?", video.filename),
+                "Choisissez une piste",
+                &options,
+            )
+            .await?
+            .parse()?;
+            streams
+                .iter()
+                .find(|s| s.id == id)
+                .ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error))?
+        }
+    };
+
+    // Keeping the original codec is lossless and instant
+    let mut options = Vec::new();
+    let copy = stream.codec.as_deref().and_then(VideoContainer::from_audio_codec);
+    if let (Some(container), Some(codec)) = (copy, &stream.codec) {
+        options.push((
+            format!("Original sans perte ({} en {})", codec, container.get_file_extension()),
+            container.get_file_extension(),
+        ));
+    }
+    for (label, container) in FORMATS {
+        if Some(container) != copy {
+            options.push((label.to_owned(), container.get_file_extension()));
+        }
+    }
+    let container = choose(
+        cmd,
+        ctx,
+        &format!("Dans quel format extraire **{}** ?", stream.describe()),
+        "Choisissez un format",
+        &options,
+    )
+    .await?;
+    let container = VideoContainer::from_file_extension(&container)
+        .ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error))?;
+
+    let transcoded = !matches!(&stream.codec, Some(c) if container.accepts_codec(&StreamKind::Audio, c));
+    let bit_rate = if transcoded && !container.is_lossless() {
+        let options: Vec<_> = BIT_RATES
+            .iter()
+            .map(|b| (format!("{} kb/s", b), b.to_string()))
+            .collect();
+        let kbps: u32 = choose(cmd, ctx, "Quel débit ?", "Choisissez un débit", &options)
+            .await?
+            .parse()?;
+        Some(kbps * 1000)
+    } else {
+        None
+    };
+
+    Ok(job::Parameters::ExtractAudio(ExtractAudioParameters {
+        stream: stream.id,
+        container,
+        bit_rate,
+    }))
+}
@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use serenity::{
+    model::prelude::interaction::{
+        application_command::ApplicationCommandInteraction,
+        message_component::MessageComponentInteraction,
+    },
+    prelude::Context,
+};
+
+use models::{error, job, FadeParameters};
+
+use crate::utils::modal::{ask, parse_optional};
+
+pub async fn get_info(
+    cmd: &ApplicationCommandInteraction,
+    interaction_reponse: &MessageComponentInteraction,
+    ctx: &Context,
+) -> Result<job::Parameters, error::Interaction> {
+    let values = ask(
+        cmd,
+        interaction_reponse,
+        ctx,
+        "Durée des fondus (en secondes)",
+        &[("Fondu d'ouverture", "Ex: 1.5"), ("Fondu de fermeture", "Ex: 2")],
+    )
+    .await?;
+
+    let seconds = |value: &str| -> Result<Option<Duration>, error::Interaction> {
+        let seconds: Option<f64> = parse_optional(&value.replace(',', "."))?;
+        Ok(seconds.filter(|s| *s > 0.0 && s.is_finite()).map(Duration::from_secs_f64))
+    };
+    let params = FadeParameters {
+        fade_in: seconds(&values[0])?,
+        fade_out: seconds(&values[1])?,
+    };
+    if params.fade_in.is_none() && params.fade_out.is_none() {
+        return Err(error::Interaction::InvalidInput(error::InvalidInput::Error));
+    }
+
+    Ok(job::Parameters::Fade(params))
+}
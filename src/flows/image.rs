@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use serenity::{
+    model::prelude::interaction::{
+        application_command::ApplicationCommandInteraction,
+        message_component::MessageComponentInteraction,
+    },
+    prelude::Context,
+};
+
+use models::{error, job, ImageFormat, ImageKind, ImageParameters};
+
+use crate::{
+    commands::edit::{EditMessage, GetMessage},
+    utils::{self, modal::ask, select::choose},
+};
+
+pub async fn get_info(
+    cmd: &ApplicationCommandInteraction,
+    interaction_reponse: &MessageComponentInteraction,
+    ctx: &Context,
+) -> Result<job::Parameters, error::Interaction> {
+    interaction_reponse.defer(&ctx.http).await?;
+
+    let sender_message = cmd.get_message()?;
+
+    cmd.edit_original_interaction_response(&ctx.http, |m| {
+        m.content(format!(
+            "Quelle image tirer de **{}** ?",
+            sender_message.attachments[0].filename
+        ));
+        m.components(|comps| {
+            comps.create_action_row(|row| {
+                row.create_select_menu(|menu| {
+                    menu.custom_id("image");
+                    menu.placeholder("Choisissez une image");
+                    menu.options(|f| {
+                        f.create_option(|o| o.label("Image à un instant...").value("frame"));
+                        f.create_option(|o| o.label("Miniature automatique").value("thumbnail"));
+                        f.create_option(|o| o.label("Planche contact 3x3").value("3x3"));
+                        f.create_option(|o| o.label("Planche contact 4x4").value("4x4"))
+                    })
+                })
+            })
+        })
+    })
+    .await?;
+
+    // Await image choice (with timeout)
+    let interaction_reponse = &cmd.get_interaction_response(&ctx.http).await?;
+    let Some(interaction) = interaction_reponse
+        .await_component_interaction(ctx)
+        .timeout(Duration::from_secs(60 * 3))
+        .await else {
+        cmd.edit(&ctx.http, "T trop lent, j'ai pas ton temps").await?;
+        return Err(error::Interaction::Timeout);
+    };
+
+    let kind = match interaction.data.values[0].as_str() {
+        "frame" => {
+            let values = ask(cmd, &interaction, ctx, "Instant de l'image", &[("Instant", "Ex: 01:23.5")]).await?;
+            ImageKind::Frame(utils::durationparser::parse(&values[0])?.to_std()?)
+        }
+        "thumbnail" => {
+            interaction.defer(&ctx.http).await?;
+            ImageKind::Thumbnail
+        }
+        grid => {
+            interaction.defer(&ctx.http).await?;
+            let (columns, rows) = grid
+                .split_once('x')
+                .ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error))?;
+            ImageKind::ContactSheet { columns: columns.parse()?, rows: rows.parse()? }
+        }
+    };
+
+    let formats: Vec<_> = ImageFormat::ALL
+        .iter()
+        .map(|f| (f.get_name().to_owned(), f.get_file_extension()))
+        .collect();
+    let format = choose(cmd, ctx, "Dans quel format ?", "Choisissez un format", &formats).await?;
+    let format = ImageFormat::ALL
+        .into_iter()
+        .find(|f| f.get_file_extension() == format)
+        .ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error))?;
+
+    Ok(job::Parameters::Image(ImageParameters { kind, format }))
+}
@@ -0,0 +1,52 @@
+use serenity::{
+    model::prelude::interaction::{
+        application_command::ApplicationCommandInteraction,
+        message_component::MessageComponentInteraction,
+    },
+    prelude::Context,
+};
+
+use models::{error, job, LoudnessParameters, LoudnessTarget, VolumeAdjustment};
+
+use crate::{commands::edit::GetMessage, utils::select::choose};
+
+const ADJUSTMENTS: [(&str, &str, VolumeAdjustment); 7] = [
+    ("Normaliser à -14 LUFS (YouTube, Spotify...)", "streaming", VolumeAdjustment::Normalize(LoudnessTarget::STREAMING)),
+    ("Normaliser à -23 LUFS (TV, EBU R128)", "broadcast", VolumeAdjustment::Normalize(LoudnessTarget::BROADCAST)),
+    ("Monter le volume (+6 dB)", "gain_6", VolumeAdjustment::Gain(6.0)),
+    ("Monter le volume (+3 dB)", "gain_3", VolumeAdjustment::Gain(3.0)),
+    ("Baisser le volume (-3 dB)", "gain_-3", VolumeAdjustment::Gain(-3.0)),
+    ("Baisser le volume (-6 dB)", "gain_-6", VolumeAdjustment::Gain(-6.0)),
+    ("Couper le son", "mute", VolumeAdjustment::Mute),
+];
+
+pub async fn get_info(
+    cmd: &ApplicationCommandInteraction,
+    interaction_reponse: &MessageComponentInteraction,
+    ctx: &Context,
+) -> Result<job::Parameters, error::Interaction> {
+    interaction_reponse.defer(&ctx.http).await?;
+
+    let sender_message = cmd.get_message()?;
+
+    let options: Vec<_> = ADJUSTMENTS
+        .iter()
+        .map(|(label, value, _)| (label.to_string(), value.to_string()))
+        .collect();
+    let choice = choose(
+        cmd,
+        ctx,
+        &format!("Comment régler le volume de **{}** ?", sender_message.attachments[0].filename),
+        "Choisissez un réglage",
+        &options,
+    )
+    .await?;
+
+    let adjustment = ADJUSTMENTS
+        .into_iter()
+        .find(|(_, value, _)| *value == choice)
+        .map(|(_, _, adjustment)| adjustment)
+        .ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error))?;
+
+    Ok(job::Parameters::Loudness(LoudnessParameters { adjustment }))
+}
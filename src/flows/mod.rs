@@ -2,4 +2,18 @@ pub mod encode_to_size;
 pub mod cut;
 pub mod remux;
 pub mod combine;
-pub mod speed;
\ No newline at end of file
+pub mod speed;
+pub mod transform;
+pub mod orient;
+pub mod animation;
+pub mod extract_audio;
+pub mod loudness;
+pub mod concat;
+pub mod overlay;
+pub mod subtitle;
+pub mod image;
+pub mod reverse;
+pub mod fade;
+pub mod split;
+pub mod trim;
+pub mod scenes;
@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use serenity::{
+    model::prelude::interaction::{
+        application_command::ApplicationCommandInteraction,
+        message_component::MessageComponentInteraction,
+    },
+    prelude::Context,
+};
+
+use models::{error, job, OrientParameters, Orientation, Rotation};
+
+use crate::commands::edit::{EditMessage, GetMessage};
+
+const ORIENTATIONS: [(&str, &str, Orientation); 6] = [
+    ("Tourner de 90° vers la droite", "clockwise", Orientation::Rotate(Rotation::Clockwise)),
+    ("Tourner de 180°", "upside_down", Orientation::Rotate(Rotation::UpsideDown)),
+    ("Tourner de 90° vers la gauche", "counter_clockwise", Orientation::Rotate(Rotation::CounterClockwise)),
+    ("Miroir horizontal", "flip_horizontal", Orientation::FlipHorizontal),
+    ("Miroir vertical", "flip_vertical", Orientation::FlipVertical),
+    ("Corriger la rotation (vidéo de téléphone)", "bake", Orientation::Bake),
+];
+
+pub async fn get_info(
+    cmd: &ApplicationCommandInteraction,
+    interaction_reponse: &MessageComponentInteraction,
+    ctx: &Context,
+) -> Result<job::Parameters, error::Interaction> {
+    interaction_reponse.defer(&ctx.http).await?;
+
+    let sender_message = cmd.get_message()?;
+
+    cmd.edit_original_interaction_response(&ctx.http, |m| {
+        m.content(format!(
+            "Comment tourner **{}** ?",
+            sender_message.attachments[0].filename
+        ));
+        m.components(|comps| {
+            comps.create_action_row(|row| {
+                row.create_select_menu(|menu| {
+                    menu.custom_id("orientation");
+                    menu.placeholder("Choisissez une rotation");
+                    menu.options(|f| {
+                        for (label, value, _) in ORIENTATIONS {
+                            f.create_option(|o| o.label(label).value(value));
+                        }
+                        f
+                    })
+                })
+            })
+        })
+    })
+    .await?;
+
+    // Await orientation choice (with timeout)
+    let interaction_reponse = &cmd.get_interaction_response(&ctx.http).await?;
+    let Some(interaction) = interaction_reponse
+        .await_component_interaction(ctx)
+        .timeout(Duration::from_secs(60 * 3))
+        .await else {
+        cmd.edit(&ctx.http, "T trop lent, j'ai pas ton temps").await?;
+        return Err(error::Interaction::Timeout);
+    };
+    interaction.defer(&ctx.http).await?;
+
+    let orientation = ORIENTATIONS
+        .into_iter()
+        .find(|(_, value, _)| *value == interaction.data.values[0])
+        .map(|(_, _, orientation)| orientation)
+        .ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error))?;
+
+    Ok(job::Parameters::Orient(OrientParameters { orientation }))
+}
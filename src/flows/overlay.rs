@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+use serenity::{
+    model::prelude::interaction::{
+        application_command::ApplicationCommandInteraction,
+        message_component::MessageComponentInteraction,
+    },
+    prelude::Context,
+};
+
+use models::{error, job, Font, ImageOverlay, Overlay, OverlayParameters, Position, TextOverlay, VideoURI};
+
+use crate::{
+    commands::edit::{EditMessage, GetMessage},
+    utils::{self, modal::{ask_text, parse_optional}, reply::await_attachment, select::choose},
+};
+
+pub async fn get_info(
+    cmd: &ApplicationCommandInteraction,
+    interaction_reponse: &MessageComponentInteraction,
+    ctx: &Context,
+) -> Result<job::Parameters, error::Interaction> {
+    interaction_reponse.defer(&ctx.http).await?;
+
+    let sender_message = cmd.get_message()?;
+
+    cmd.edit_original_interaction_response(&ctx.http, |m| {
+        m.content(format!(
+            "Que faut-il ajouter sur **{}** ?",
+            sender_message.attachments[0].filename
+        ));
+        m.components(|comps| {
+            comps.create_action_row(|row| {
+                row.create_select_menu(|menu| {
+                    menu.custom_id("overlay");
+                    menu.placeholder("Choisissez un ajout");
+                    menu.options(|f| {
+                        f.create_option(|o| o.label("Du texte...").value("text"));
+                        f.create_option(|o| o.label("Une image (filigrane)").value("image"));
+                        f.create_option(|o| o.label("Une légende de meme...").value("meme"))
+                    })
+                })
+            })
+        })
+    })
+    .await?;
+
+    // Await overlay choice (with timeout)
+    let interaction_reponse = &cmd.get_interaction_response(&ctx.http).await?;
+    let Some(interaction) = interaction_reponse
+        .await_component_interaction(ctx)
+        .timeout(Duration::from_secs(60 * 3))
+        .await else {
+        cmd.edit(&ctx.http, "T trop lent, j'ai pas ton temps").await?;
+        return Err(error::Interaction::Timeout);
+    };
+
+    let overlay = match interaction.data.values[0].as_str() {
+        "text" => Overlay::Text(ask_overlay_text(cmd, &interaction, ctx).await?),
+        "image" => {
+            interaction.defer(&ctx.http).await?;
+            Overlay::Image(ask_image(cmd, ctx).await?)
+        }
+        _ => {
+            let values = ask_text(cmd, &interaction, ctx, "Légende du meme", &[("Texte", "Ex: Quand le rendu marche du premier coup")]).await?;
+            Overlay::Meme { text: values[0].to_owned() }
+        }
+    };
+
+    Ok(job::Parameters::Overlay(OverlayParameters { overlay }))
+}
+
+async fn ask_position(cmd: &ApplicationCommandInteraction, ctx: &Context) -> Result<Position, error::Interaction> {
+    let options: Vec<(String, String)> = Position::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (p.get_name().to_owned(), i.to_string()))
+        .collect();
+    let choice: usize = choose(cmd, ctx, "Où placer l'ajout ?", "Choisissez une position", &options).await?.parse()?;
+    Position::ALL
+        .get(choice)
+        .copied()
+        .ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error))
+}
+
+async fn ask_overlay_text(
+    cmd: &ApplicationCommandInteraction,
+    interaction: &MessageComponentInteraction,
+    ctx: &Context,
+) -> Result<TextOverlay, error::Interaction> {
+    let values = ask_text(
+        cmd,
+        interaction,
+        ctx,
+        "Texte à ajouter",
+        &[
+            ("Texte", "Ex: Bonjour"),
+            ("Taille (% de la hauteur)", "Ex: 8"),
+            ("Couleur", "Ex: white, #ff0000"),
+            ("Début", "Ex: 00:05"),
+            ("Fin", "Ex: 00:10"),
+        ],
+    )
+    .await?;
+    let timestamp = |value: &String| -> Result<Option<std::time::Duration>, error::Interaction> {
+        match value.is_empty() {
+            true => Ok(None),
+            false => Ok(Some(utils::durationparser::parse(value)?.to_std()?)),
+        }
+    };
+    let start = timestamp(&values[3])?;
+    let end = timestamp(&values[4])?;
+
+    let position = ask_position(cmd, ctx).await?;
+
+    let fonts: Vec<(String, String)> = Font::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, f)| (f.get_name().to_owned(), i.to_string()))
+        .collect();
+    let font: usize = choose(cmd, ctx, "Quelle police ?", "Choisissez une police", &fonts).await?.parse()?;
+    let font = *Font::ALL.get(font).ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error))?;
+
+    let styles = [
+        ("Contour noir".to_owned(), "outline".to_owned()),
+        ("Fond noir".to_owned(), "box".to_owned()),
+        ("Aucun".to_owned(), "none".to_owned()),
+    ];
+    let style = choose(cmd, ctx, "Quel style pour le texte ?", "Choisissez un style", &styles).await?;
+
+    Ok(TextOverlay {
+        text: values[0].to_owned(),
+        position,
+        font,
+        size: parse_optional(&values[1])?.unwrap_or(8),
+        color: Some(values[2].to_owned()).filter(|c| !c.is_empty()).unwrap_or("white".to_owned()),
+        box_color: (style == "box").then(|| "black".to_owned()),
+        outline: style == "outline",
+        start,
+        end,
+    })
+}
+
+async fn ask_image(cmd: &ApplicationCommandInteraction, ctx: &Context) -> Result<ImageOverlay, error::Interaction> {
+    let attachment = await_attachment(cmd, ctx, "Répondez à ce message avec l'image à ajouter").await?;
+    let url = VideoURI::Url(attachment.url);
+
+    let position = ask_position(cmd, ctx).await?;
+
+    let opacities: Vec<(String, String)> = [100, 75, 50, 25]
+        .iter()
+        .map(|o| (format!("Opacité {o}%"), o.to_string()))
+        .collect();
+    let opacity: u32 = choose(cmd, ctx, "Quelle opacité ?", "Choisissez une opacité", &opacities).await?.parse()?;
+
+    Ok(ImageOverlay {
+        url,
+        position,
+        opacity: opacity as f64 / 100.0,
+        width: 20,
+    })
+}
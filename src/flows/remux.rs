@@ -38,8 +38,11 @@ pub async fn get_info(
                     menu.custom_id("container");
                     menu.placeholder("Choisissez un format");
                     menu.options(|f| {
-                        f.create_option(|o| o.label("mp4").value("mp4"));
-                        f.create_option(|o| o.label("mkv").value("mkv"))
+                        for container in VideoContainer::ALL {
+                            let extension = container.get_file_extension();
+                            f.create_option(|o| o.label(&extension).value(&extension));
+                        }
+                        f
                     })
                 })
             })
@@ -57,10 +60,9 @@ pub async fn get_info(
     };
 
     // Get edit kind from awaited interaction
-    let container = match interaction.data.values[0].as_str() {
-        "mp4" => VideoContainer::MP4,
-        "mkv" => VideoContainer::MKV,
-        _ => {
+    let container = match VideoContainer::from_file_extension(&interaction.data.values[0]) {
+        Some(c) => c,
+        None => {
             return Err(error::Interaction::InvalidInput(
                 error::InvalidInput::Error,
             ))
@@ -0,0 +1,64 @@
+use serenity::{
+    model::prelude::interaction::{
+        application_command::ApplicationCommandInteraction,
+        message_component::MessageComponentInteraction,
+    },
+    prelude::Context,
+};
+
+use models::{error, job, ReverseMode, ReverseParameters};
+
+use crate::{commands::edit::GetMessage, utils::select::choose};
+
+/// Effects offered as `(label, boomerang, loops)`
+const EFFECTS: [(&str, bool, u32); 3] = [
+    ("À l'envers", false, 1),
+    ("Boomerang (aller-retour)", true, 1),
+    ("Boomerang répété 3 fois", true, 3),
+];
+
+pub async fn get_info(
+    cmd: &ApplicationCommandInteraction,
+    interaction_reponse: &MessageComponentInteraction,
+    ctx: &Context,
+) -> Result<job::Parameters, error::Interaction> {
+    interaction_reponse.defer(&ctx.http).await?;
+
+    let sender_message = cmd.get_message()?;
+
+    let modes = [
+        ("Image et son".to_owned(), "both".to_owned()),
+        ("Image seulement".to_owned(), "video".to_owned()),
+        ("Son seulement".to_owned(), "audio".to_owned()),
+    ];
+    let mode = match choose(
+        cmd,
+        ctx,
+        &format!("Que faut-il passer à l'envers dans **{}** ?", sender_message.attachments[0].filename),
+        "Choisissez les pistes",
+        &modes,
+    )
+    .await?
+    .as_str()
+    {
+        "video" => ReverseMode::Video,
+        "audio" => ReverseMode::Audio,
+        _ => ReverseMode::Both,
+    };
+
+    let effects: Vec<_> = EFFECTS
+        .iter()
+        .enumerate()
+        .map(|(i, (label, _, _))| (label.to_string(), i.to_string()))
+        .collect();
+    let effect: usize = choose(cmd, ctx, "Quel effet ?", "Choisissez un effet", &effects).await?.parse()?;
+    let (_, boomerang, loops) = EFFECTS
+        .get(effect)
+        .ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error))?;
+
+    Ok(job::Parameters::Reverse(ReverseParameters {
+        mode,
+        boomerang: *boomerang,
+        loops: *loops,
+    }))
+}
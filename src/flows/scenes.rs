@@ -0,0 +1,58 @@
+use serenity::{
+    model::prelude::interaction::{
+        application_command::ApplicationCommandInteraction,
+        message_component::MessageComponentInteraction,
+    },
+    prelude::Context,
+};
+
+use models::{error, job, SceneAction, SceneParameters};
+
+use crate::{commands::edit::GetMessage, utils::select::choose};
+
+/// Sensitivities offered as `(label, threshold)`, a lower threshold finds more scenes
+const SENSITIVITIES: [(&str, f64); 3] = [
+    ("Normale", SceneParameters::DEFAULT_THRESHOLD),
+    ("Élevée (plus de scènes)", 0.3),
+    ("Faible (moins de scènes)", 0.5),
+];
+
+pub async fn get_info(
+    cmd: &ApplicationCommandInteraction,
+    interaction_reponse: &MessageComponentInteraction,
+    ctx: &Context,
+) -> Result<job::Parameters, error::Interaction> {
+    interaction_reponse.defer(&ctx.http).await?;
+
+    let sender_message = cmd.get_message()?;
+
+    let actions = [
+        ("Ajouter un chapitre par scène".to_owned(), "chapters".to_owned()),
+        ("Découper à chaque scène".to_owned(), "split".to_owned()),
+    ];
+    let action = match choose(
+        cmd,
+        ctx,
+        &format!("Que faire des scènes de **{}** ?", sender_message.attachments[0].filename),
+        "Choisissez une action",
+        &actions,
+    )
+    .await?
+    .as_str()
+    {
+        "split" => SceneAction::Split,
+        _ => SceneAction::Chapters,
+    };
+
+    let sensitivities: Vec<_> = SENSITIVITIES
+        .iter()
+        .enumerate()
+        .map(|(i, (label, _))| (label.to_string(), i.to_string()))
+        .collect();
+    let sensitivity: usize = choose(cmd, ctx, "Quelle sensibilité ?", "Choisissez une sensibilité", &sensitivities).await?.parse()?;
+    let (_, threshold) = SENSITIVITIES
+        .get(sensitivity)
+        .ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error))?;
+
+    Ok(job::Parameters::Scenes(SceneParameters { threshold: *threshold, action }))
+}
@@ -11,12 +11,12 @@ use serenity::{
     prelude::Context,
 };
 
-use models::{error, job, SpeedParameters, Video};
+use models::{error, job, SpeedMode, SpeedParameters, Video};
 use tokio_stream::StreamExt;
 
 use crate::{
     commands::edit::EditMessage,
-    utils::{self, durationparser::DisplayTimestamp},
+    utils::{self, durationparser::DisplayTimestamp, select::choose},
 };
 
 pub async fn get_info(
@@ -56,9 +56,9 @@ pub async fn get_info(
                     let stream = res.first().ok_or(error::Interaction::Error)?;
                     break stream.duration;
                 }
+                job::Response::Cut(_) | job::Response::DeadAir(_) | job::Response::Scenes(_) => {}
             },
-            job::Progress::Progress(_) => todo!(),
-            job::Progress::Done(_) => todo!(),
+            job::Progress::Progress(_) | job::Progress::Done { .. } => {}
         }
     };
 
@@ -132,7 +132,7 @@ pub async fn get_info(
         _ => return Err(error::Interaction::Error),
     };
     let parsed = utils::durationparser::parse(end)?;
-    let speed_factor = if parsed.is_zero() {
+    let speed_factor: f64 = if parsed.is_zero() {
         match &interaction.data.components[1].components[0] {
             ActionRowComponent::InputText(txt) => &txt.value,
             _ => return Err(error::Interaction::Error),
@@ -146,5 +146,20 @@ pub async fn get_info(
 
     // Ack modal interaction
     interaction.defer(&ctx.http).await?;
-    Ok(job::Parameters::Speed(SpeedParameters { speed_factor }))
+    if speed_factor <= 0.0 || !speed_factor.is_finite() {
+        cmd.edit(&ctx.http, "La vitesse doit être positive").await?;
+        return Err(error::Interaction::InvalidInput(error::InvalidInput::Error));
+    }
+
+    // Slow motion only makes sense when slowing down
+    let modes: Vec<_> = SpeedMode::ALL
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| speed_factor < 1.0 || !matches!(m, SpeedMode::SlowMotion { .. }))
+        .map(|(i, m)| (m.get_name().to_owned(), i.to_string()))
+        .collect();
+    let mode: usize = choose(cmd, ctx, "Comment changer la vitesse ?", "Choisissez une méthode", &modes).await?.parse()?;
+    let mode = *SpeedMode::ALL.get(mode).ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error))?;
+
+    Ok(job::Parameters::Speed(SpeedParameters { speed_factor, mode }))
 }
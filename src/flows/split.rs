@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use serenity::{
+    model::prelude::interaction::{
+        application_command::ApplicationCommandInteraction,
+        message_component::MessageComponentInteraction,
+    },
+    prelude::Context,
+};
+
+use models::{error, job, SplitMode, SplitParameters};
+
+use crate::{
+    commands::edit::{get_upload_limit, EditMessage, GetMessage},
+    utils::{self, modal::{ask, ask_text}},
+};
+
+pub async fn get_info(
+    cmd: &ApplicationCommandInteraction,
+    interaction_reponse: &MessageComponentInteraction,
+    ctx: &Context,
+) -> Result<job::Parameters, error::Interaction> {
+    interaction_reponse.defer(&ctx.http).await?;
+
+    let sender_message = cmd.get_message()?;
+
+    cmd.edit_original_interaction_response(&ctx.http, |m| {
+        m.content(format!(
+            "Comment découper **{}** ?",
+            sender_message.attachments[0].filename
+        ));
+        m.components(|comps| {
+            comps.create_action_row(|row| {
+                row.create_select_menu(|menu| {
+                    menu.custom_id("split");
+                    menu.placeholder("Choisissez un découpage");
+                    menu.options(|f| {
+                        f.create_option(|o| o.label("En parties égales...").value("parts"));
+                        f.create_option(|o| o.label("Par durée...").value("length"));
+                        f.create_option(|o| o.label("Pour passer sous la limite de discord").value("size"));
+                        f.create_option(|o| o.label("Aux moments choisis...").value("timestamps"))
+                    })
+                })
+            })
+        })
+    })
+    .await?;
+
+    // Await split choice (with timeout)
+    let interaction_reponse = &cmd.get_interaction_response(&ctx.http).await?;
+    let Some(interaction) = interaction_reponse
+        .await_component_interaction(ctx)
+        .timeout(Duration::from_secs(60 * 3))
+        .await else {
+        cmd.edit(&ctx.http, "T trop lent, j'ai pas ton temps").await?;
+        return Err(error::Interaction::Timeout);
+    };
+
+    let mode = match interaction.data.values[0].as_str() {
+        "parts" => {
+            let values = ask(cmd, &interaction, ctx, "Découper en parties égales", &[("Nombre de parties", "Ex: 3")]).await?;
+            let count: u32 = values[0].parse()?;
+            if !(2..=SplitParameters::MAX_PARTS as u32).contains(&count) {
+                cmd.edit(&ctx.http, &format!("Il faut entre 2 et {} parties", SplitParameters::MAX_PARTS)).await?;
+                return Err(error::Interaction::InvalidInput(error::InvalidInput::Error));
+            }
+            SplitMode::Parts(count)
+        }
+        "length" => {
+            let values = ask(cmd, &interaction, ctx, "Découper par durée", &[("Durée de chaque partie", "Ex: 01:30")]).await?;
+            let length = utils::durationparser::parse(&values[0])?;
+            if length <= chrono::Duration::zero() {
+                return Err(error::Interaction::InvalidInput(error::InvalidInput::Error));
+            }
+            SplitMode::Length(length.to_std()?)
+        }
+        "size" => {
+            interaction.defer(&ctx.http).await?;
+            SplitMode::Size(get_upload_limit(cmd, ctx).await)
+        }
+        _ => {
+            let values = ask_text(cmd, &interaction, ctx, "Découper aux moments choisis", &[("Début de chaque partie", "Ex: 01:00; 02:30")]).await?;
+            let mut timestamps = Vec::new();
+            for timestamp in values[0].split(['\n', ';']).map(str::trim).filter(|t| !t.is_empty()) {
+                timestamps.push(utils::durationparser::parse(&timestamp.to_owned())?.to_std()?);
+            }
+            if timestamps.is_empty() {
+                return Err(error::Interaction::InvalidInput(error::InvalidInput::Error));
+            }
+            SplitMode::Timestamps(timestamps)
+        }
+    };
+
+    Ok(job::Parameters::Split(SplitParameters { mode }))
+}
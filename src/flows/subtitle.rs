@@ -0,0 +1,161 @@
+use serenity::{
+    model::prelude::interaction::{
+        application_command::ApplicationCommandInteraction,
+        message_component::MessageComponentInteraction,
+    },
+    prelude::Context,
+};
+
+use models::{
+    error, job, Font, StreamKind, SubtitleFile, SubtitleFormat, SubtitleOperation, SubtitleParameters,
+    SubtitleSource, SubtitleStyle, Video, VideoContainer, VideoURI,
+};
+
+use crate::{
+    commands::edit::{fetch_streams, EditMessage},
+    utils::{reply::await_attachment, select::choose},
+};
+
+/// Text colors offered for burned subtitles
+const COLORS: [(&str, [u8; 3]); 2] = [("Blanc", [255, 255, 255]), ("Jaune", [255, 221, 0])];
+
+const LANGUAGES: [(&str, &str); 2] = [("Français", "fre"), ("Anglais", "eng")];
+
+pub async fn get_info(
+    cmd: &ApplicationCommandInteraction,
+    interaction_reponse: &MessageComponentInteraction,
+    ctx: &Context,
+    video: &Video,
+) -> Result<job::Parameters, error::Interaction> {
+    interaction_reponse.defer(&ctx.http).await?;
+
+    let operations = [
+        ("Extraire une piste de sous-titres".to_owned(), "extract".to_owned()),
+        ("Convertir un fichier de sous-titres".to_owned(), "convert".to_owned()),
+        ("Incruster des sous-titres dans la vidéo".to_owned(), "burn".to_owned()),
+        ("Ajouter un fichier de sous-titres".to_owned(), "mux".to_owned()),
+    ];
+    let operation = choose(
+        cmd,
+        ctx,
+        &format!("Que faire des sous-titres de **{}** ?", video.filename),
+        "Choisissez une opération",
+        &operations,
+    )
+    .await?;
+
+    let operation = match operation.as_str() {
+        "extract" => SubtitleOperation::Extract {
+            source: ask_stream(cmd, ctx, video, false).await?,
+            format: ask_format(cmd, ctx).await?,
+        },
+        "convert" => SubtitleOperation::Extract {
+            source: SubtitleSource::File(ask_file(cmd, ctx).await?),
+            format: ask_format(cmd, ctx).await?,
+        },
+        "burn" => SubtitleOperation::Burn {
+            source: ask_stream(cmd, ctx, video, true).await?,
+            style: ask_style(cmd, ctx).await?,
+        },
+        _ => {
+            let file = ask_file(cmd, ctx).await?;
+            let mut options: Vec<_> = LANGUAGES.iter().map(|(name, code)| (name.to_string(), code.to_string())).collect();
+            options.push(("Non précisée".to_owned(), String::new()));
+            let language = choose(cmd, ctx, "Quelle est la langue des sous-titres ?", "Choisissez une langue", &options).await?;
+            // Mp4 can only hold text subtitles as mov_text, anything else goes to mkv
+            let container = match video.get_container() {
+                Some(c) if c.is_mp4_family() && c != VideoContainer::M4A => VideoContainer::MP4,
+                _ => VideoContainer::MKV,
+            };
+            SubtitleOperation::Mux {
+                file,
+                container,
+                language: Some(language).filter(|l| !l.is_empty()),
+            }
+        }
+    };
+
+    Ok(job::Parameters::Subtitle(SubtitleParameters { operation }))
+}
+
+async fn ask_file(cmd: &ApplicationCommandInteraction, ctx: &Context) -> Result<SubtitleFile, error::Interaction> {
+    let attachment = await_attachment(cmd, ctx, "Répondez à ce message avec le fichier de sous-titres (.srt, .vtt, .ass)").await?;
+    Ok(SubtitleFile {
+        url: VideoURI::Url(attachment.url),
+        filename: attachment.filename,
+    })
+}
+
+/// Subtitle track of the video, or an attached file when `allow_file` is set
+async fn ask_stream(
+    cmd: &ApplicationCommandInteraction,
+    ctx: &Context,
+    video: &Video,
+    allow_file: bool,
+) -> Result<SubtitleSource, error::Interaction> {
+    let streams: Vec<_> = fetch_streams(cmd, ctx, video)
+        .await?
+        .into_iter()
+        .filter(|s| s.kind == StreamKind::Subtitle)
+        .collect();
+
+    if streams.is_empty() && !allow_file {
+        cmd.edit(&ctx.http, &format!("**{}** n'a pas de piste de sous-titres", video.filename))
+            .await?;
+        return Err(error::Interaction::InvalidInput(error::InvalidInput::Error));
+    }
+    let mut options: Vec<_> = streams
+        .iter()
+        .map(|s| (format!("Piste {}: {}", s.id, s.describe()), s.id.to_string()))
+        .collect();
+    if allow_file {
+        options.push(("Un fichier (.srt, .vtt, .ass)".to_owned(), "file".to_owned()));
+    }
+    let choice = match &options[..] {
+        [(_, value)] => value.to_owned(),
+        _ => choose(cmd, ctx, "Quels sous-titres utiliser ?", "Choisissez des sous-titres", &options).await?,
+    };
+
+    match choice.as_str() {
+        "file" => Ok(SubtitleSource::File(ask_file(cmd, ctx).await?)),
+        id => Ok(SubtitleSource::Stream(id.parse()?)),
+    }
+}
+
+async fn ask_format(cmd: &ApplicationCommandInteraction, ctx: &Context) -> Result<SubtitleFormat, error::Interaction> {
+    let options: Vec<_> = SubtitleFormat::ALL
+        .iter()
+        .map(|f| (f.get_file_extension().to_uppercase(), f.get_file_extension()))
+        .collect();
+    let extension = choose(cmd, ctx, "Dans quel format ?", "Choisissez un format", &options).await?;
+    SubtitleFormat::from_file_extension(&extension).ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error))
+}
+
+async fn ask_style(cmd: &ApplicationCommandInteraction, ctx: &Context) -> Result<SubtitleStyle, error::Interaction> {
+    let mut options = Vec::new();
+    for (i, (name, _)) in COLORS.iter().enumerate() {
+        options.push((format!("{name}, contour noir"), format!("{i}:outline")));
+        options.push((format!("{name}, fond noir"), format!("{i}:box")));
+    }
+    let choice = choose(cmd, ctx, "Quel style pour les sous-titres ?", "Choisissez un style", &options).await?;
+    let (color, background) = choice
+        .split_once(':')
+        .ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error))?;
+    let (_, color) = COLORS
+        .get(color.parse::<usize>()?)
+        .ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error))?;
+
+    let positions = [
+        ("En bas".to_owned(), "bottom".to_owned()),
+        ("En haut".to_owned(), "top".to_owned()),
+    ];
+    let position = choose(cmd, ctx, "Où placer les sous-titres ?", "Choisissez une position", &positions).await?;
+
+    Ok(SubtitleStyle {
+        font: Font::Sans,
+        size: 20,
+        color: *color,
+        outline: background == "outline",
+        top: position == "top",
+    })
+}
@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+use serenity::{
+    model::prelude::interaction::{
+        application_command::ApplicationCommandInteraction,
+        message_component::MessageComponentInteraction,
+    },
+    prelude::Context,
+};
+
+use models::{error, job, AspectRatio, Crop, Pad, PadFill, Scale, TransformParameters};
+
+use crate::{
+    commands::edit::{EditMessage, GetMessage},
+    utils::modal::{ask, parse_optional},
+};
+
+pub async fn get_info(
+    cmd: &ApplicationCommandInteraction,
+    interaction_reponse: &MessageComponentInteraction,
+    ctx: &Context,
+) -> Result<job::Parameters, error::Interaction> {
+    interaction_reponse.defer(&ctx.http).await?;
+
+    let sender_message = cmd.get_message()?;
+
+    cmd.edit_original_interaction_response(&ctx.http, |m| {
+        m.content(format!(
+            "Comment transformer **{}** ?",
+            sender_message.attachments[0].filename
+        ));
+        m.components(|comps| {
+            comps.create_action_row(|row| {
+                row.create_select_menu(|menu| {
+                    menu.custom_id("transform");
+                    menu.placeholder("Choisissez une transformation");
+                    menu.options(|f| {
+                        f.create_option(|o| {
+                            o.label("Supprimer les bandes noires").value("autocrop")
+                        });
+                        for ratio in AspectRatio::ALL {
+                            let name = ratio.get_name();
+                            f.create_option(|o| {
+                                o.label(format!("Format {} (fond flou)", name))
+                                    .value(format!("blur:{}", name))
+                            });
+                            f.create_option(|o| {
+                                o.label(format!("Format {} (bandes noires)", name))
+                                    .value(format!("black:{}", name))
+                            });
+                        }
+                        f.create_option(|o| o.label("Redimensionner...").value("scale"));
+                        f.create_option(|o| o.label("Recadrer...").value("crop"))
+                    })
+                })
+            })
+        })
+    })
+    .await?;
+
+    // Await transform choice (with timeout)
+    let interaction_reponse = &cmd.get_interaction_response(&ctx.http).await?;
+    let Some(interaction) = interaction_reponse
+        .await_component_interaction(ctx)
+        .timeout(Duration::from_secs(60 * 3))
+        .await else {
+        cmd.edit(&ctx.http, "T trop lent, j'ai pas ton temps").await?;
+        return Err(error::Interaction::Timeout);
+    };
+
+    let mut params = TransformParameters {
+        crop: None,
+        scale: None,
+        pad: None,
+    };
+    let choice = interaction.data.values[0].as_str();
+    match choice {
+        "autocrop" => params.crop = Some(Crop::Auto),
+        "scale" => params.scale = Some(ask_scale(cmd, &interaction, ctx).await?),
+        "crop" => params.crop = Some(ask_crop(cmd, &interaction, ctx).await?),
+        _ => {
+            let (fill, ratio) = choice
+                .split_once(':')
+                .ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error))?;
+            let aspect_ratio = AspectRatio::ALL
+                .into_iter()
+                .find(|r| r.get_name() == ratio)
+                .ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error))?;
+            let fill = match fill {
+                "blur" => PadFill::Blur,
+                _ => PadFill::Color("black".to_owned()),
+            };
+            params.pad = Some(Pad { aspect_ratio, fill });
+        }
+    }
+    // Modals were already acked
+    if !matches!(choice, "scale" | "crop") {
+        interaction.defer(&ctx.http).await?;
+    }
+
+    Ok(job::Parameters::Transform(params))
+}
+
+async fn ask_scale(
+    cmd: &ApplicationCommandInteraction,
+    interaction: &MessageComponentInteraction,
+    ctx: &Context,
+) -> Result<Scale, error::Interaction> {
+    let values = ask(
+        cmd,
+        interaction,
+        ctx,
+        "Nouvelle taille de la vidéo",
+        &[
+            ("Largeur", "Ex: 1280"),
+            ("Hauteur", "Ex: 720"),
+            ("Ou pourcentage", "Ex: 50%"),
+        ],
+    )
+    .await?;
+
+    let width = parse_optional(&values[0])?;
+    let height = parse_optional(&values[1])?;
+    if width.is_some() || height.is_some() {
+        return Ok(Scale::Size { width, height });
+    }
+    match parse_optional(values[2].trim_end_matches('%').trim())? {
+        Some(percent) => Ok(Scale::Percent(percent)),
+        None => Err(error::Interaction::InvalidInput(error::InvalidInput::Error)),
+    }
+}
+
+async fn ask_crop(
+    cmd: &ApplicationCommandInteraction,
+    interaction: &MessageComponentInteraction,
+    ctx: &Context,
+) -> Result<Crop, error::Interaction> {
+    let values = ask(
+        cmd,
+        interaction,
+        ctx,
+        "Zone à garder (en pixels)",
+        &[
+            ("X", "Depuis la gauche, ex: 0"),
+            ("Y", "Depuis le haut, ex: 140"),
+            ("Largeur", "Ex: 1920"),
+            ("Hauteur", "Ex: 800"),
+        ],
+    )
+    .await?;
+
+    let values = values
+        .iter()
+        .map(|v| parse_optional(v)?.ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Crop::Rect {
+        x: values[0],
+        y: values[1],
+        width: values[2],
+        height: values[3],
+    })
+}
@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+use serenity::{
+    model::prelude::interaction::{
+        application_command::ApplicationCommandInteraction,
+        message_component::MessageComponentInteraction,
+    },
+    prelude::Context,
+};
+
+use models::{error, job, DeadAir, Detection, TrimParameters, Video};
+
+use crate::{
+    commands::edit::{detect_dead_air, EditMessage},
+    utils::{durationparser::DisplayTimestamp, modal::{ask, parse_optional}},
+};
+
+/// Ranges listed in the report, the others are only counted
+const REPORT_MAX_LINES: usize = 15;
+
+pub async fn get_info(
+    cmd: &ApplicationCommandInteraction,
+    interaction_reponse: &MessageComponentInteraction,
+    ctx: &Context,
+    video: &Video,
+) -> Result<job::Parameters, error::Interaction> {
+    interaction_reponse.defer(&ctx.http).await?;
+
+    cmd.edit_original_interaction_response(&ctx.http, |m| {
+        m.content(format!("Quels temps morts retirer de **{}** ?", video.filename));
+        m.components(|comps| {
+            comps.create_action_row(|row| {
+                row.create_select_menu(|menu| {
+                    menu.custom_id("trim");
+                    menu.placeholder("Choisissez les temps morts");
+                    menu.options(|f| {
+                        f.create_option(|o| o.label("Les silences...").value("silence"));
+                        f.create_option(|o| o.label("Les images noires...").value("black"));
+                        f.create_option(|o| o.label("Les images figées...").value("freeze"));
+                        f.create_option(|o| o.label("Tout...").value("all"))
+                    })
+                })
+            })
+        })
+    })
+    .await?;
+
+    // Await detection choice (with timeout)
+    let interaction_reponse = &cmd.get_interaction_response(&ctx.http).await?;
+    let Some(interaction) = interaction_reponse
+        .await_component_interaction(ctx)
+        .timeout(Duration::from_secs(60 * 3))
+        .await else {
+        cmd.edit(&ctx.http, "T trop lent, j'ai pas ton temps").await?;
+        return Err(error::Interaction::Timeout);
+    };
+    let choice = interaction.data.values[0].to_owned();
+    let silence = choice == "silence" || choice == "all";
+    let black = choice == "black" || choice == "all";
+    let freeze = choice == "freeze" || choice == "all";
+
+    // Thresholds are only asked for the chosen detections, empty fields keep the defaults
+    let mut inputs = vec![("Durée minimale (secondes)", "Ex: 1")];
+    if silence {
+        inputs.push(("Seuil du silence (dB)", "Ex: -50"));
+    }
+    if black {
+        inputs.push(("Seuil du noir (%)", "Ex: 10"));
+    }
+    if freeze {
+        inputs.push(("Seuil du figé (dB)", "Ex: -60"));
+    }
+    let values = ask(cmd, &interaction, ctx, "Réglages de la détection", &inputs).await?;
+    let mut values = values.iter().map(|v| v.replace(',', "."));
+
+    let min_duration: Option<f64> = parse_optional(&values.next().unwrap_or_default())?;
+    let min_duration = min_duration.filter(|d| *d > 0.0 && d.is_finite()).map(Duration::from_secs_f64);
+    let mut detection = |enabled: bool, default: Detection, scale: f64| -> Result<Option<Detection>, error::Interaction> {
+        if !enabled {
+            return Ok(None);
+        }
+        let threshold: Option<f64> = parse_optional(&values.next().unwrap_or_default())?;
+        Ok(Some(Detection {
+            threshold: threshold.filter(|t| t.is_finite()).map(|t| t * scale).unwrap_or(default.threshold),
+            min_duration: min_duration.unwrap_or(default.min_duration),
+        }))
+    };
+    let params = TrimParameters {
+        silence: detection(silence, Detection::SILENCE, 1.0)?,
+        black: detection(black, Detection::BLACK, 0.01)?,
+        freeze: detection(freeze, Detection::FREEZE, 1.0)?,
+    };
+
+    let found = detect_dead_air(cmd, ctx, video, &params).await?;
+    if found.is_empty() {
+        cmd.edit(&ctx.http, &format!("Aucun temps mort trouvé dans **{}**", video.filename)).await?;
+        return Err(error::Interaction::InvalidInput(error::InvalidInput::Error));
+    }
+
+    cmd.edit_original_interaction_response(&ctx.http, |m| {
+        m.content(format!("Retirer de **{}**:\n{}", video.filename, describe(&found)));
+        m.components(|comps| {
+            comps.create_action_row(|r| {
+                r.create_button(|b| b.custom_id("confirm").label("Valider"));
+                r.create_button(|b| b.custom_id("cancel").label("Annuler"))
+            })
+        })
+    })
+    .await?;
+
+    // Await confirmation (with timeout)
+    let interaction_reponse = &cmd.get_interaction_response(&ctx.http).await?;
+    let Some(interaction) = interaction_reponse
+        .await_component_interaction(ctx)
+        .timeout(Duration::from_secs(60 * 3))
+        .await else {
+        cmd.edit(&ctx.http, "T trop lent, j'ai pas ton temps").await?;
+        return Err(error::Interaction::Timeout);
+    };
+    interaction.defer(&ctx.http).await?;
+    if interaction.data.custom_id != "confirm" {
+        cmd.edit(&ctx.http, "Retrait annulé").await?;
+        return Err(error::Interaction::InvalidInput(error::InvalidInput::Error));
+    }
+
+    Ok(job::Parameters::Trim(params))
+}
+
+/// One line per range of dead air, ex. `Silence: 00:01.500 → 00:03.250`
+fn describe(found: &[DeadAir]) -> String {
+    let timestamp = |d: Duration| {
+        chrono::Duration::from_std(d)
+            .ok()
+            .and_then(|d| d.display_timestamp().ok())
+            .unwrap_or_default()
+    };
+    let mut lines: Vec<String> = found
+        .iter()
+        .take(REPORT_MAX_LINES)
+        .map(|d| format!("{}: {} → {}", d.kind.get_name(), timestamp(d.start), timestamp(d.end)))
+        .collect();
+    if found.len() > REPORT_MAX_LINES {
+        lines.push(format!("... et {} autres", found.len() - REPORT_MAX_LINES));
+    }
+    lines.join("\n")
+}
@@ -72,6 +72,17 @@ async fn main() {
     // Configure the client with your Discord bot token in the environment.
     let token = env::var("IVE_DISCORD_TOKEN").expect("Expected a token in the environment");
 
+    // Delete outputs that were only sent as a link once the link expired
+    tokio::spawn(async {
+        let expiry = config::get_presign_expiry();
+        loop {
+            if let Err(why) = storage::delete_expired(storage::get_storage().as_ref(), storage::OUTPUTS_PREFIX, expiry).await {
+                println!("Cleanup error: {:?}", why);
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(60 * 60)).await;
+        }
+    });
+
     // Build our client.
     let mut client = Client::builder(token, GatewayIntents::GUILD_MESSAGES)
         .event_handler(Handler)
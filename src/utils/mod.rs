@@ -1 +1,4 @@
-pub mod durationparser;
\ No newline at end of file
+pub mod durationparser;
+pub mod modal;
+pub mod reply;
+pub mod select;
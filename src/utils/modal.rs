@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use serenity::{
+    model::prelude::{
+        component::{ActionRow, ActionRowComponent, InputTextStyle},
+        interaction::{
+            application_command::ApplicationCommandInteraction,
+            message_component::MessageComponentInteraction, InteractionResponseType,
+        },
+    },
+    prelude::Context,
+};
+
+use models::error;
+
+use crate::commands::edit::EditMessage;
+
+/// Displays a modal with one short text input per label, returning the values
+pub async fn ask(
+    cmd: &ApplicationCommandInteraction,
+    interaction: &MessageComponentInteraction,
+    ctx: &Context,
+    title: &str,
+    inputs: &[(&str, &str)],
+) -> Result<Vec<String>, error::Interaction> {
+    show(cmd, interaction, ctx, title, inputs, false).await
+}
+
+/// Same as [`ask`], but the first input is a multiline text
+pub async fn ask_text(
+    cmd: &ApplicationCommandInteraction,
+    interaction: &MessageComponentInteraction,
+    ctx: &Context,
+    title: &str,
+    inputs: &[(&str, &str)],
+) -> Result<Vec<String>, error::Interaction> {
+    show(cmd, interaction, ctx, title, inputs, true).await
+}
+
+async fn show(
+    cmd: &ApplicationCommandInteraction,
+    interaction: &MessageComponentInteraction,
+    ctx: &Context,
+    title: &str,
+    inputs: &[(&str, &str)],
+    text: bool,
+) -> Result<Vec<String>, error::Interaction> {
+    interaction
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::Modal)
+                .interaction_response_data(|modal| {
+                    modal
+                        .custom_id("edit_modal")
+                        .title(title)
+                        .components(|comp| {
+                            for (i, (label, placeholder)) in inputs.iter().enumerate() {
+                                comp.create_action_row(|row| {
+                                    row.create_input_text(|menu| {
+                                        menu.custom_id(format!("edit_modal_{}", i));
+                                        menu.placeholder(*placeholder);
+                                        menu.label(*label);
+                                        if text && i == 0 {
+                                            menu.style(InputTextStyle::Paragraph);
+                                            menu.max_length(500);
+                                        } else {
+                                            menu.style(InputTextStyle::Short);
+                                            menu.max_length(10);
+                                        }
+                                        menu.required(false)
+                                    })
+                                });
+                            }
+                            comp
+                        })
+                })
+        })
+        .await?;
+
+    // Get message of interaction reponse
+    let interaction_reponse = &interaction.get_interaction_response(&ctx.http).await?;
+
+    // Await modal reponse
+    let Some(modal) = interaction_reponse
+        .await_modal_interaction(ctx)
+        .timeout(Duration::from_secs(60 * 3))
+        .await else {
+        cmd.edit(&ctx.http, "T trop lent, j'ai pas ton temps").await?;
+        return Err(error::Interaction::Timeout);
+    };
+
+    let values = modal
+        .data
+        .components
+        .iter()
+        .map(get_value)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Ack modal interaction
+    modal.defer(&ctx.http).await?;
+    Ok(values)
+}
+
+fn get_value(row: &ActionRow) -> Result<String, error::Interaction> {
+    match row.components.first() {
+        Some(ActionRowComponent::InputText(txt)) => Ok(txt.value.trim().to_owned()),
+        _ => Err(error::Interaction::Error),
+    }
+}
+
+/// Empty fields are `None`
+pub fn parse_optional<T>(value: &str) -> Result<Option<T>, error::Interaction>
+where
+    T: std::str::FromStr,
+    error::Interaction: From<T::Err>,
+{
+    if value.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(value.parse()?))
+}
@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use serenity::{
+    model::prelude::{interaction::application_command::ApplicationCommandInteraction, Attachment},
+    prelude::Context,
+};
+
+use models::error;
+
+use crate::commands::edit::EditMessage;
+
+/// Asks the user to reply to the interaction message with a file, returning its first attachment
+pub async fn await_attachment(
+    cmd: &ApplicationCommandInteraction,
+    ctx: &Context,
+    content: &str,
+) -> Result<Attachment, error::Interaction> {
+    cmd.edit_original_interaction_response(&ctx.http, |m| {
+        m.content(content);
+        m.components(|c| c)
+    })
+    .await?;
+
+    // Await reply (with timeout)
+    let interaction_id = cmd.id;
+    let reply = cmd
+        .user
+        .await_reply(ctx)
+        .filter(move |x| {
+            !x.attachments.is_empty()
+                && matches!(x.referenced_message.as_ref().and_then(|m| m.interaction.as_ref()), Some(i) if i.id == interaction_id)
+        })
+        .timeout(Duration::from_secs(60 * 3))
+        .await;
+    let Some(reply) = reply else {
+        cmd.edit(&ctx.http, "T trop lent, j'ai pas ton temps").await?;
+        return Err(error::Interaction::Timeout);
+    };
+    Ok(reply.attachments[0].to_owned())
+}
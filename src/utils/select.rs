@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use serenity::{
+    model::prelude::interaction::application_command::ApplicationCommandInteraction,
+    prelude::Context,
+};
+
+use models::error;
+
+use crate::commands::edit::EditMessage;
+
+/// Replaces the interaction message with a select menu of `(label, value)` options,
+/// returning the chosen value
+pub async fn choose(
+    cmd: &ApplicationCommandInteraction,
+    ctx: &Context,
+    content: &str,
+    placeholder: &str,
+    options: &[(String, String)],
+) -> Result<String, error::Interaction> {
+    cmd.edit_original_interaction_response(&ctx.http, |m| {
+        m.content(content);
+        m.components(|comps| {
+            comps.create_action_row(|row| {
+                row.create_select_menu(|menu| {
+                    menu.custom_id("choice");
+                    menu.placeholder(placeholder);
+                    menu.options(|f| {
+                        for (label, value) in options {
+                            f.create_option(|o| o.label(label).value(value));
+                        }
+                        f
+                    })
+                })
+            })
+        })
+    })
+    .await?;
+
+    // Await choice (with timeout)
+    let interaction_reponse = &cmd.get_interaction_response(&ctx.http).await?;
+    let Some(interaction) = interaction_reponse
+        .await_component_interaction(ctx)
+        .timeout(Duration::from_secs(60 * 3))
+        .await else {
+        cmd.edit(&ctx.http, "T trop lent, j'ai pas ton temps").await?;
+        return Err(error::Interaction::Timeout);
+    };
+    interaction.defer(&ctx.http).await?;
+
+    interaction
+        .data
+        .values
+        .first()
+        .cloned()
+        .ok_or(error::Interaction::InvalidInput(error::InvalidInput::Error))
+}